@@ -1,12 +1,381 @@
 use anyhow::{Context, Result};
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tracing::warn;
 
 pub struct ApiClient;
 
+/// Attempts per Semgrep App request, including the first one.
+const MAX_ATTEMPTS: u32 = 3;
+/// Backoff before the first retry; doubled for each further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound on a server-requested `Retry-After` delay.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// Largest findings page the API serves.
+const FINDINGS_PAGE_SIZE: u32 = 3000;
+/// Findings pages read when counting; counts past this many findings are marked truncated.
+const MAX_COUNT_PAGES: u32 = 20;
+
+trait SendWithRetry {
+    async fn send_with_retry(self) -> reqwest::Result<Response>;
+}
+
+impl SendWithRetry for RequestBuilder {
+    /// Sends the request, retrying rate limits, server errors, and connection failures with
+    /// backoff. Only GETs are retried: a write that timed out may already have been applied.
+    async fn send_with_retry(self) -> reqwest::Result<Response> {
+        let Some((method, path)) = self.try_clone().and_then(|b| b.build().ok()).map(|r| (r.method().clone(), r.url().path().to_string())) else {
+            return self.send().await;
+        };
+        if method != Method::GET {
+            return self.send().await;
+        }
+        let mut attempt = 1;
+        loop {
+            let Some(request) = self.try_clone() else { return self.send().await };
+            let result = request.send().await;
+            let reason = match &result {
+                Ok(r) if r.status() == StatusCode::TOO_MANY_REQUESTS || r.status().is_server_error() => format!("status {}", r.status()),
+                Err(e) if e.is_connect() || e.is_timeout() => e.to_string(),
+                _ => return result,
+            };
+            if attempt >= MAX_ATTEMPTS {
+                return result;
+            }
+
+            let retry_after = result
+                .as_ref()
+                .ok()
+                .and_then(|r| r.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.parse::<u64>().ok())
+                .map(|secs| Duration::from_secs(secs).min(MAX_RETRY_DELAY));
+            let delay = retry_after.unwrap_or(RETRY_BASE_DELAY * 2u32.pow(attempt - 1));
+            warn!("Semgrep API request to {} failed ({}); retrying in {:?} (attempt {}/{})", path, reason, delay, attempt + 1, MAX_ATTEMPTS);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Product area a finding belongs to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueType {
+    Sast,
+    Sca,
+}
+
+/// Current status of a finding in the Semgrep App.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingStatus {
+    Open,
+    Reviewing,
+    Fixing,
+    Ignored,
+    Fixed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+/// Verdict of Semgrep Assistant's automatic triage.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AutotriageVerdict {
+    TruePositive,
+    FalsePositive,
+}
+
+/// Filters accepted by the findings endpoint.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FindingsQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_type: Option<IssueType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<FindingStatus>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repos: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub severities: Vec<Severity>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autotriage_verdict: Option<AutotriageVerdict>,
+    /// Only return findings first seen at or after this timestamp (RFC 3339 or epoch seconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
+
+impl FindingsQuery {
+    /// Flattens the query into URL parameters, joining list filters with commas.
+    pub fn to_query_params(&self) -> Vec<(&'static str, String)> {
+        fn enum_str<T: Serialize>(value: &T) -> String {
+            serde_json::to_value(value).ok().and_then(|v| v.as_str().map(|s| s.to_string())).unwrap_or_default()
+        }
+
+        let mut params = Vec::new();
+        if let Some(v) = &self.issue_type { params.push(("issue_type", enum_str(v))); }
+        if let Some(v) = &self.status { params.push(("status", enum_str(v))); }
+        if !self.repos.is_empty() { params.push(("repos", self.repos.join(","))); }
+        if !self.severities.is_empty() {
+            params.push(("severities", self.severities.iter().map(enum_str).collect::<Vec<_>>().join(",")));
+        }
+        if !self.rules.is_empty() { params.push(("rules", self.rules.join(","))); }
+        if let Some(v) = &self.confidence { params.push(("confidence", enum_str(v))); }
+        if let Some(v) = &self.autotriage_verdict { params.push(("autotriage_verdict", enum_str(v))); }
+        if let Some(v) = &self.since { params.push(("since", v.clone())); }
+        if let Some(v) = &self.git_ref { params.push(("ref", v.clone())); }
+        if let Some(v) = self.dedup { params.push(("dedup", v.to_string())); }
+        if let Some(v) = self.page { params.push(("page", v.to_string())); }
+        if let Some(v) = self.page_size { params.push(("page_size", v.to_string())); }
+        params
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FindingsResponse {
+    #[serde(default)]
+    pub findings: Vec<Finding>,
+}
+
+/// A single finding as returned by the findings endpoint.
+///
+/// Only the fields sidero relies on are typed; everything else is kept in `extra`
+/// so the full payload can still be returned verbatim.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Finding {
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(default, rename = "ref")]
+    pub git_ref: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub confidence: Option<String>,
+    #[serde(default)]
+    pub rule_name: Option<String>,
+    #[serde(default)]
+    pub rule_message: Option<String>,
+    #[serde(default)]
+    pub repository: Option<FindingRepository>,
+    #[serde(default)]
+    pub location: Option<FindingLocation>,
+    #[serde(default)]
+    pub triage_state: Option<String>,
+    #[serde(default)]
+    pub relevant_since: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FindingRepository {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FindingLocation {
+    #[serde(default)]
+    pub file_path: Option<String>,
+    #[serde(default)]
+    pub line: Option<u64>,
+    #[serde(default)]
+    pub column: Option<u64>,
+    #[serde(default)]
+    pub end_line: Option<u64>,
+    #[serde(default)]
+    pub end_column: Option<u64>,
+}
+
+/// Compact, token-friendly view of a [`Finding`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FindingSummary {
+    pub id: Option<u64>,
+    pub rule: Option<String>,
+    pub severity: Option<String>,
+    pub status: Option<String>,
+    pub repo: Option<String>,
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autotriage_verdict: Option<String>,
+    pub message: Option<String>,
+}
+
+impl From<&Finding> for FindingSummary {
+    fn from(finding: &Finding) -> Self {
+        let location = finding.location.as_ref().and_then(|loc| {
+            loc.file_path.as_ref().map(|path| match loc.line {
+                Some(line) => format!("{}:{}", path, line),
+                None => path.clone(),
+            })
+        });
+        let autotriage_verdict = finding
+            .extra
+            .get("assistant")
+            .and_then(|a| a.pointer("/autotriage/verdict"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        FindingSummary {
+            id: finding.id,
+            rule: finding.rule_name.clone(),
+            severity: finding.severity.clone(),
+            status: finding.status.clone().or_else(|| finding.triage_state.clone()),
+            repo: finding.repository.as_ref().and_then(|r| r.name.clone()),
+            git_ref: finding.git_ref.clone(),
+            location,
+            autotriage_verdict,
+            message: finding.rule_message.clone(),
+        }
+    }
+}
+
+/// A project (repository) onboarded to the Semgrep App.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Project {
+    #[serde(default)]
+    pub id: Option<u64>,
+    pub name: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub latest_scan_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+/// Open finding counts for a project, grouped by severity.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FindingCounts {
+    pub total: usize,
+    pub by_severity: BTreeMap<String, usize>,
+    /// Set when there were more open findings than sidero reads, so the counts are too low.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Says in words that the counts are truncated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Compact view of a [`Project`] with optional finding counts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectSummary {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub latest_scan_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_findings: Option<FindingCounts>,
+}
+
+impl ProjectSummary {
+    pub fn new(project: &Project, open_findings: Option<FindingCounts>) -> Self {
+        ProjectSummary {
+            name: project.name.clone(),
+            tags: project.tags.clone(),
+            latest_scan_at: project.latest_scan_at.clone(),
+            url: project.url.clone(),
+            open_findings,
+        }
+    }
+}
+
+/// Triage state a finding can be moved to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageState {
+    Ignored,
+    Reviewing,
+    Fixing,
+    Reopened,
+}
+
+/// Why a finding was ignored.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageReason {
+    FalsePositive,
+    AcceptableRisk,
+    NoTime,
+}
+
+/// Body of the bulk triage endpoint.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TriageRequest {
+    pub issue_type: IssueType,
+    pub issue_ids: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_triage_state: Option<TriageState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_triage_reason: Option<TriageReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_note: Option<String>,
+}
+
 impl ApiClient {
-    pub async fn get_findings(token: &str, params: serde_json::Map<String, Value>) -> Result<Value> {
+    /// The Semgrep App token: `SEMGREP_APP_TOKEN`, or the one `semgrep login` saved in
+    /// Semgrep's settings file (`SEMGREP_SETTINGS_FILE`, default `~/.semgrep/settings.yml`).
+    pub fn token() -> Option<String> {
+        if let Some(token) = std::env::var("SEMGREP_APP_TOKEN").ok().filter(|t| !t.is_empty()) {
+            return Some(token);
+        }
+        let contents = std::fs::read_to_string(Self::settings_file()?).ok()?;
+        contents.lines().find_map(|line| {
+            let token = line.strip_prefix("api_token:")?.trim().trim_matches(|c| c == '"' || c == '\'');
+            (!token.is_empty()).then(|| token.to_string())
+        })
+    }
+
+    /// Where `semgrep login` saves the token.
+    pub fn settings_file() -> Option<PathBuf> {
+        std::env::var_os("SEMGREP_SETTINGS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".semgrep/settings.yml")))
+    }
+
+    pub async fn get_findings(token: &str, query: &FindingsQuery) -> Result<FindingsResponse> {
         let client = Client::new();
         
         let slug = Self::get_deployment_slug(&client, token).await?;
@@ -17,8 +386,8 @@ impl ApiClient {
             .get(&url)
             .bearer_auth(token)
             .header("Accept", "application/json")
-            .query(&params)
-            .send()
+            .query(&query.to_query_params())
+            .send_with_retry()
             .await
             .context("Failed to send request to Semgrep Findings API")?;
 
@@ -28,11 +397,160 @@ impl ApiClient {
              anyhow::bail!("API request failed with status {}: {}", status, text);
         }
 
-        let json: Value = response.json().await.context("Failed to parse filings API response")?;
+        let findings: FindingsResponse = response.json().await.context("Failed to parse findings API response")?;
+        Ok(findings)
+    }
+
+    pub async fn triage_findings(token: &str, request: &TriageRequest) -> Result<Value> {
+        let client = Client::new();
+
+        let slug = Self::get_deployment_slug(&client, token).await?;
+
+        let url = format!("https://semgrep.dev/api/v1/deployments/{}/triage", slug);
+
+        let response = client
+            .post(&url)
+            .bearer_auth(token)
+            .header("Accept", "application/json")
+            .json(request)
+            .send_with_retry()
+            .await
+            .context("Failed to send request to Semgrep Triage API")?;
+
+        if !response.status().is_success() {
+             let status = response.status();
+             let text = response.text().await.unwrap_or_default();
+             anyhow::bail!("API request failed with status {}: {}", status, text);
+        }
+
+        let json: Value = response.json().await.context("Failed to parse triage API response")?;
         Ok(json)
     }
 
+    pub async fn list_projects(token: &str) -> Result<Vec<Project>> {
+        #[derive(Deserialize)]
+        struct ProjectsResponse {
+            projects: Vec<Project>,
+        }
+
+        let client = Client::new();
+
+        let slug = Self::get_deployment_slug(&client, token).await?;
+
+        let url = format!("https://semgrep.dev/api/v1/deployments/{}/projects", slug);
+
+        let response = client
+            .get(&url)
+            .bearer_auth(token)
+            .header("Accept", "application/json")
+            .send_with_retry()
+            .await
+            .context("Failed to send request to Semgrep Projects API")?;
+
+        if !response.status().is_success() {
+             let status = response.status();
+             let text = response.text().await.unwrap_or_default();
+             anyhow::bail!("API request failed with status {}: {}", status, text);
+        }
+
+        let data: ProjectsResponse = response.json().await.context("Failed to parse projects API response")?;
+        Ok(data.projects)
+    }
+
+    pub async fn get_project(token: &str, name: &str) -> Result<Project> {
+        #[derive(Deserialize)]
+        struct ProjectResponse {
+            project: Project,
+        }
+
+        let client = Client::new();
+
+        let slug = Self::get_deployment_slug(&client, token).await?;
+
+        // Project names usually contain a slash ("org/repo"), so push them as a single encoded segment.
+        let mut url = reqwest::Url::parse("https://semgrep.dev/api/v1/deployments")?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid projects URL"))?
+            .push(&slug)
+            .push("projects")
+            .push(name);
+
+        let response = client
+            .get(url)
+            .bearer_auth(token)
+            .header("Accept", "application/json")
+            .send_with_retry()
+            .await
+            .context("Failed to send request to Semgrep Projects API")?;
+
+        if !response.status().is_success() {
+             let status = response.status();
+             let text = response.text().await.unwrap_or_default();
+             anyhow::bail!("API request failed with status {}: {}", status, text);
+        }
+
+        let data: ProjectResponse = response.json().await.context("Failed to parse project API response")?;
+        Ok(data.project)
+    }
+
+    /// Counts open findings per project, paging through them once. An empty `repos` counts
+    /// every project; projects without open findings are left out.
+    pub async fn get_open_finding_counts(token: &str, repos: &[String]) -> Result<BTreeMap<String, FindingCounts>> {
+        let mut counts: BTreeMap<String, FindingCounts> = BTreeMap::new();
+        let mut truncated = true;
+        for page in 0..MAX_COUNT_PAGES {
+            let query = FindingsQuery {
+                status: Some(FindingStatus::Open),
+                repos: repos.to_vec(),
+                dedup: Some(true),
+                page: Some(page),
+                page_size: Some(FINDINGS_PAGE_SIZE),
+                ..Default::default()
+            };
+            let res = Self::get_findings(token, &query).await?;
+            for finding in &res.findings {
+                let repo = finding.repository.as_ref().and_then(|r| r.name.clone()).unwrap_or_else(|| "unknown".to_string());
+                let severity = finding.severity.clone().unwrap_or_else(|| "unknown".to_string());
+                let project = counts.entry(repo).or_default();
+                project.total += 1;
+                *project.by_severity.entry(severity).or_default() += 1;
+            }
+            if res.findings.len() < FINDINGS_PAGE_SIZE as usize {
+                truncated = false;
+                break;
+            }
+        }
+
+        if truncated {
+            let read = FINDINGS_PAGE_SIZE * MAX_COUNT_PAGES;
+            for project in counts.values_mut() {
+                project.truncated = true;
+                project.note = Some(format!("Counted from the first {} open findings only; the real counts are higher", read));
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Checks a token against the API, returning the slug of its deployment.
+    pub async fn verify_token(token: &str) -> Result<String> {
+        Self::get_deployment_slug(&Client::new(), token).await
+    }
+
+    /// Whether semgrep.dev can be reached at all; any HTTP response counts.
+    pub async fn check_reachable() -> Result<()> {
+        let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
+        client.head("https://semgrep.dev").send().await.context("Failed to reach semgrep.dev")?;
+        Ok(())
+    }
+
+    /// The slug of the token's deployment, fetched once per token.
     async fn get_deployment_slug(client: &Client, token: &str) -> Result<String> {
+        static SLUGS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+        let slugs = SLUGS.get_or_init(Default::default);
+        if let Some(slug) = slugs.lock().unwrap().get(token) {
+            return Ok(slug.clone());
+        }
+
         #[derive(Deserialize)]
         struct Deployment {
             slug: String,
@@ -47,7 +565,7 @@ impl ApiClient {
             .get(url)
             .bearer_auth(token)
             .header("Accept", "application/json")
-            .send()
+            .send_with_retry()
             .await
             .context("Failed to fetch deployments")?;
 
@@ -57,16 +575,18 @@ impl ApiClient {
 
         let data: DeploymentsResponse = response.json().await.context("Failed to parse deployments response")?;
         
-        data.deployments.first()
+        let slug = data.deployments.first()
             .map(|d| d.slug.clone())
-            .ok_or_else(|| anyhow::anyhow!("No deployments found for this token"))
+            .ok_or_else(|| anyhow::anyhow!("No deployments found for this token"))?;
+        slugs.lock().unwrap().insert(token.to_string(), slug.clone());
+        Ok(slug)
     }
 
     pub async fn fetch_url(url: &str) -> Result<String> {
         let client = Client::new();
         let response = client
             .get(url)
-            .send()
+            .send_with_retry()
             .await
             .context(format!("Failed to fetch URL: {}", url))?;
 
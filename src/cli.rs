@@ -0,0 +1,263 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::client_log::ClientLog;
+use crate::config::Config;
+use crate::doctor::DoctorReport;
+use crate::notifier::Notifier;
+use crate::protocol::Content;
+use crate::scan_store::ScanStore;
+use crate::semgrep_wrapper::SemgrepWrapper;
+use crate::subscriptions::SubscriptionManager;
+use crate::tools::{ToolContext, ToolRegistry};
+use crate::watch::WatchManager;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the MCP server over stdio (the default)
+    Serve,
+    /// Check the Semgrep installation and sidero's environment
+    Doctor {
+        /// Workspace root to check for Semgrep config [default: current directory]
+        #[arg(long)]
+        workspace: Option<PathBuf>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Scan files with a rule config or a single rule file (the semgrep_scan tools)
+    Scan(ScanArgs),
+    /// Fetch findings from the Semgrep App (the semgrep_findings tool)
+    Findings(FindingsArgs),
+    /// Print the Semgrep AST of a file (the get_abstract_syntax_tree tool)
+    Ast(AstArgs),
+    /// Scan for leaked credentials (the semgrep_secrets_scan tool)
+    Secrets(SecretsArgs),
+    /// Check lockfiles against the advisory database (the semgrep_supply_chain_scan tool)
+    SupplyChain(SupplyChainArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ScanArgs {
+    /// Files or directories to scan
+    #[arg(required = true)]
+    paths: Vec<String>,
+    /// Rule configuration: a registry pack (p/ci), a rule file or directory, local/<pack>, or local-rule/<id>
+    #[arg(long)]
+    config: Option<String>,
+    /// Scan with the rule YAML in this file instead of a config
+    #[arg(long, conflicts_with = "config")]
+    rule: Option<PathBuf>,
+    /// Print Semgrep's JSON instead of one line per finding
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct FindingsArgs {
+    /// sast or sca
+    #[arg(long)]
+    issue_type: Option<String>,
+    /// open, reviewing, fixing, ignored, or fixed
+    #[arg(long)]
+    status: Option<String>,
+    /// Repository to include (repeatable)
+    #[arg(long = "repo")]
+    repos: Vec<String>,
+    /// low, medium, high, or critical (repeatable)
+    #[arg(long = "severity")]
+    severities: Vec<String>,
+    /// Rule ID to include (repeatable)
+    #[arg(long = "rule")]
+    rules: Vec<String>,
+    /// low, medium, or high
+    #[arg(long)]
+    confidence: Option<String>,
+    /// true_positive or false_positive
+    #[arg(long)]
+    autotriage_verdict: Option<String>,
+    /// Only findings first seen at or after this time (RFC 3339 or epoch seconds)
+    #[arg(long)]
+    since: Option<String>,
+    /// Git ref (branch) the findings were reported on
+    #[arg(long = "ref")]
+    git_ref: Option<String>,
+    /// Collapse findings that appear on several refs
+    #[arg(long)]
+    dedup: bool,
+    #[arg(long)]
+    page: Option<u32>,
+    #[arg(long)]
+    page_size: Option<u32>,
+    /// Print the full API payload instead of the compact summary
+    #[arg(long)]
+    full: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct AstArgs {
+    /// Language of the file
+    #[arg(long)]
+    lang: String,
+    /// File to parse, or - for stdin
+    file: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct SecretsArgs {
+    /// Files or directories to scan
+    #[arg(required = true)]
+    paths: Vec<String>,
+    /// Skip the built-in detectors and only run --secrets-rules
+    #[arg(long)]
+    no_builtin: bool,
+    /// Entropy threshold (bits/char) for generic high-entropy strings
+    #[arg(long)]
+    min_entropy: Option<f64>,
+}
+
+#[derive(Args, Debug)]
+pub struct SupplyChainArgs {
+    /// Files or directories to search for lockfiles
+    #[arg(required = true)]
+    paths: Vec<String>,
+    /// Also print the normalized dependency list
+    #[arg(long)]
+    include_dependencies: bool,
+}
+
+/// Runs a one-shot command through the same tool implementations the server uses, printing
+/// the tool's output. Returns the process exit code.
+pub async fn run(command: Command, config: Config) -> Result<i32> {
+    let (tool, args) = match command {
+        Command::Serve => bail!("serve is not a one-shot command"),
+        Command::Doctor { workspace, json } => {
+            let report = DoctorReport::run(&config, workspace.as_deref()).await;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report.render());
+            }
+            return Ok(if report.healthy { 0 } else { 1 });
+        }
+        Command::Scan(scan) => {
+            let json_output = scan.json;
+            let output = match scan.rule {
+                Some(rule) => {
+                    let rule = std::fs::read_to_string(&rule).with_context(|| format!("Failed to read {}", rule.display()))?;
+                    call_tool(&config, "semgrep_scan_with_custom_rule", json!({ "rule": rule, "code_files": scan.paths })).await?
+                }
+                None => call_tool(&config, "semgrep_scan", object([("paths", json!(scan.paths)), ("config", json!(scan.config))])).await?,
+            };
+            if json_output {
+                println!("{}", output);
+            } else {
+                print_scan_summary(&output)?;
+            }
+            return Ok(0);
+        }
+        Command::Findings(f) => (
+            "semgrep_findings",
+            object([
+                ("issue_type", json!(f.issue_type)),
+                ("status", json!(f.status)),
+                ("repos", json!(f.repos)),
+                ("severities", json!(f.severities)),
+                ("rules", json!(f.rules)),
+                ("confidence", json!(f.confidence)),
+                ("autotriage_verdict", json!(f.autotriage_verdict)),
+                ("since", json!(f.since)),
+                ("ref", json!(f.git_ref)),
+                ("dedup", if f.dedup { json!(true) } else { Value::Null }),
+                ("page", json!(f.page)),
+                ("page_size", json!(f.page_size)),
+                ("output", json!(if f.full { "full" } else { "summary" })),
+            ]),
+        ),
+        Command::Ast(ast) => {
+            let code = if ast.file.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin()).context("Failed to read stdin")?
+            } else {
+                std::fs::read_to_string(&ast.file).with_context(|| format!("Failed to read {}", ast.file.display()))?
+            };
+            ("get_abstract_syntax_tree", json!({ "code": code, "language": ast.lang }))
+        }
+        Command::Secrets(s) => (
+            "semgrep_secrets_scan",
+            object([("paths", json!(s.paths)), ("builtin", json!(!s.no_builtin)), ("min_entropy", json!(s.min_entropy))]),
+        ),
+        Command::SupplyChain(s) => (
+            "semgrep_supply_chain_scan",
+            json!({ "paths": s.paths, "include_dependencies": s.include_dependencies }),
+        ),
+    };
+
+    println!("{}", call_tool(&config, tool, args).await?);
+    Ok(0)
+}
+
+/// Calls a built-in tool outside of an MCP session and returns its text output.
+async fn call_tool(config: &Config, name: &str, args: Value) -> Result<String> {
+    let registry = ToolRegistry::builtin();
+    let tool = registry.get(name).with_context(|| format!("Unknown tool {}", name))?;
+    if let Some(reason) = ToolRegistry::unavailable_reason(tool.as_ref(), config) {
+        bail!("{} is not available: {}", name, reason);
+    }
+
+    // Nothing subscribes outside a session, so notifications have nowhere to go.
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let scans = Arc::new(ScanStore::new());
+    let notifier = Notifier::new(tx);
+    let semgrep = SemgrepWrapper::new(config);
+    let subscriptions = SubscriptionManager::new(scans.clone(), semgrep.clone(), notifier.clone());
+    let watches = WatchManager::new(semgrep.clone(), notifier, ClientLog::new(), subscriptions.subscribers());
+    let ctx = ToolContext { config, semgrep: &semgrep, scans: &scans, subscriptions: &subscriptions, watches: &watches };
+
+    let result = tool.call(&ctx, args).await.map_err(|e| anyhow::anyhow!(e.message))?;
+    let text = result
+        .content
+        .into_iter()
+        .filter_map(|content| match content {
+            Content::Text { text } => Some(text),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(text)
+}
+
+/// One line per finding, then a count with any scan cache hits; Semgrep errors go to stderr.
+fn print_scan_summary(output: &str) -> Result<()> {
+    let output: Value = serde_json::from_str(output).context("Semgrep returned invalid JSON")?;
+    let results = output.get("results").and_then(|r| r.as_array()).cloned().unwrap_or_default();
+    for result in &results {
+        println!(
+            "{}:{}: [{}] {}: {}",
+            result.get("path").and_then(|v| v.as_str()).unwrap_or("?"),
+            result.pointer("/start/line").and_then(|v| v.as_u64()).unwrap_or(0),
+            result.pointer("/extra/severity").and_then(|v| v.as_str()).unwrap_or("INFO"),
+            result.get("check_id").and_then(|v| v.as_str()).unwrap_or("?"),
+            result.pointer("/extra/message").and_then(|v| v.as_str()).unwrap_or("").trim(),
+        );
+    }
+    for error in output.get("errors").and_then(|e| e.as_array()).into_iter().flatten() {
+        eprintln!("semgrep error: {}", error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error").trim());
+    }
+    let hits = output.pointer("/cache/hits").and_then(|h| h.as_u64()).unwrap_or(0);
+    let misses = output.pointer("/cache/misses").and_then(|m| m.as_u64()).unwrap_or(0);
+    let cached = if hits > 0 { format!(" ({} of {} files from the scan cache)", hits, hits + misses) } else { String::new() };
+    println!("{} finding{}{}", results.len(), if results.len() == 1 { "" } else { "s" }, cached);
+    Ok(())
+}
+
+/// Tool arguments without the options that were not given.
+fn object<const N: usize>(fields: [(&str, Value); N]) -> Value {
+    let map: Map<String, Value> = fields
+        .into_iter()
+        .filter(|(_, value)| !value.is_null() && value.as_array().is_none_or(|a| !a.is_empty()))
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+    Value::Object(map)
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use crate::notifier::Notifier;
+
+/// Only events from sidero itself are forwarded; dependencies stay on stderr.
+const FORWARDED_TARGET: &str = "sidero";
+
+/// MCP log levels (RFC 5424 severities), least severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl LogLevel {
+    const ALL: [LogLevel; 8] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Notice,
+        LogLevel::Warning,
+        LogLevel::Error,
+        LogLevel::Critical,
+        LogLevel::Alert,
+        LogLevel::Emergency,
+    ];
+
+    fn from_tracing(level: &Level) -> Self {
+        match *level {
+            Level::ERROR => LogLevel::Error,
+            Level::WARN => LogLevel::Warning,
+            Level::INFO => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+}
+
+/// Forwards `tracing` events to the client as `notifications/message`.
+///
+/// Nothing is sent until the client has finished initializing; after that, events at or
+/// above the level chosen with `logging/setLevel` (warning until the client picks one) go out.
+#[derive(Clone, Default)]
+pub struct ClientLog {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    level: AtomicU8,
+    /// Set while forwarding is active. Taken on shutdown so the output channel can close.
+    notifier: Mutex<Option<Notifier>>,
+}
+
+impl Default for Inner {
+    fn default() -> Self {
+        Self { level: AtomicU8::new(LogLevel::Warning as u8), notifier: Mutex::new(None) }
+    }
+}
+
+impl ClientLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn layer(&self) -> ClientLogLayer {
+        ClientLogLayer { log: self.clone() }
+    }
+
+    /// Starts forwarding through `notifier`; called once the client has sent `notifications/initialized`.
+    pub fn activate(&self, notifier: Notifier) {
+        *self.inner.notifier.lock().unwrap() = Some(notifier);
+    }
+
+    /// Stops forwarding and releases the notifier.
+    pub fn shutdown(&self) {
+        self.inner.notifier.lock().unwrap().take();
+    }
+
+    pub fn set_level(&self, level: LogLevel) {
+        self.inner.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    fn level(&self) -> LogLevel {
+        LogLevel::ALL[self.inner.level.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Sends structured `data` as a log message, subject to the same level filter as
+    /// forwarded events.
+    pub fn log(&self, level: LogLevel, logger: &str, data: Value) {
+        if level < self.level() {
+            return;
+        }
+        let Some(notifier) = self.inner.notifier.lock().unwrap().clone() else { return };
+        notifier.notify("notifications/message", Some(json!({ "level": level, "logger": logger, "data": data })));
+    }
+}
+
+pub struct ClientLogLayer {
+    log: ClientLog,
+}
+
+impl<S: Subscriber> Layer<S> for ClientLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = LogLevel::from_tracing(metadata.level());
+        if level < self.log.level() || !metadata.target().starts_with(FORWARDED_TARGET) {
+            return;
+        }
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let data = match visitor.fields.len() {
+            0 => Value::String(visitor.message),
+            _ => {
+                let mut fields = visitor.fields;
+                fields.insert("message".to_string(), Value::String(visitor.message));
+                Value::Object(fields)
+            }
+        };
+
+        self.log.log(level, metadata.target(), data);
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.insert(field.name().to_string(), Value::String(value.to_string()));
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.insert(field.name().to_string(), Value::String(format!("{:?}", value)));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::Result;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+use crate::api_client::ApiClient;
+use crate::config::Config;
+use crate::protocol::{CompleteParams, Completion, CompletionReference};
+use crate::rule_library::LOCAL_RULE_PREFIX;
+use crate::rule_packs::{list_local_packs, LOCAL_PACK_PREFIX};
+use crate::scan_store::ScanStore;
+use crate::semgrep_wrapper::SemgrepWrapper;
+
+/// The most values a `completion/complete` response may carry.
+const MAX_VALUES: usize = 100;
+
+/// How long fetched completion data is served before it is refreshed in the background.
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Well-known Semgrep Registry packs.
+const REGISTRY_PACKS: &[&str] = &[
+    "auto",
+    "p/default",
+    "p/security-audit",
+    "p/secrets",
+    "p/owasp-top-ten",
+    "p/cwe-top-25",
+    "p/ci",
+    "p/r2c-security-audit",
+    "p/insecure-transport",
+    "p/jwt",
+    "p/sql-injection",
+    "p/xss",
+    "p/command-injection",
+    "p/gitleaks",
+    "p/supply-chain",
+    "p/python",
+    "p/django",
+    "p/flask",
+    "p/fastapi",
+    "p/javascript",
+    "p/typescript",
+    "p/nodejs",
+    "p/express",
+    "p/react",
+    "p/nextjs",
+    "p/java",
+    "p/spring",
+    "p/kotlin",
+    "p/scala",
+    "p/golang",
+    "p/gosec",
+    "p/ruby",
+    "p/rails",
+    "p/php",
+    "p/laravel",
+    "p/csharp",
+    "p/c",
+    "p/rust",
+    "p/swift",
+    "p/docker",
+    "p/dockerfile",
+    "p/docker-compose",
+    "p/kubernetes",
+    "p/terraform",
+    "p/github-actions",
+];
+
+/// Answers `completion/complete` for prompt arguments and resource template variables.
+pub struct CompletionProvider {
+    config: Config,
+    scans: Arc<ScanStore>,
+    languages: CachedList,
+    projects: CachedList,
+}
+
+impl CompletionProvider {
+    pub fn new(config: Config, scans: Arc<ScanStore>) -> Self {
+        Self { config, scans, languages: CachedList::default(), projects: CachedList::default() }
+    }
+
+    pub async fn complete(&self, params: &CompleteParams) -> Completion {
+        let candidates = self.candidates(params).await;
+        let prefix = params.argument.value.to_lowercase();
+
+        let mut matches: Vec<String> = Vec::new();
+        for candidate in candidates {
+            if candidate.to_lowercase().starts_with(&prefix) && !matches.contains(&candidate) {
+                matches.push(candidate);
+            }
+        }
+
+        let total = matches.len();
+        matches.truncate(MAX_VALUES);
+        Completion { values: matches, total: Some(total), hasMore: Some(total > MAX_VALUES) }
+    }
+
+    async fn candidates(&self, params: &CompleteParams) -> Vec<String> {
+        let argument = params.argument.name.as_str();
+        let context_scan = params.context.as_ref().and_then(|c| c.arguments.get("scan_id")).map(String::as_str);
+
+        match &params.reference {
+            CompletionReference::Resource { uri } => match (uri.as_str(), argument) {
+                ("semgrep://rule/{rule_id}/yaml", "rule_id") => self.scanned_rule_ids(),
+                ("semgrep://local-rule/{rule_id}", "rule_id") => self.saved_rule_ids(),
+                ("semgrep://scan/{scan_id}" | "semgrep://scan/{scan_id}/finding/{fingerprint}", "scan_id") => self.scan_ids(),
+                ("semgrep://scan/{scan_id}/finding/{fingerprint}", "fingerprint") => self.fingerprints(context_scan),
+                ("semgrep://projects/{+name}", "name") => self.project_names().await,
+                _ => Vec::new(),
+            },
+            CompletionReference::Prompt { .. } => match argument {
+                "language" => self.languages().await,
+                "config" => self.configs(),
+                "rule_id" => self.scanned_rule_ids(),
+                "scan_id" => self.scan_ids(),
+                "fingerprint" => self.fingerprints(context_scan),
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    async fn languages(&self) -> Vec<String> {
+        self.languages.get(SemgrepWrapper::get_supported_languages()).await
+    }
+
+    /// Saved library rules, local packs, then (when online) registry packs.
+    fn configs(&self) -> Vec<String> {
+        let mut configs: Vec<String> = self.saved_rule_ids().into_iter().map(|id| format!("{}{}", LOCAL_RULE_PREFIX, id)).collect();
+        if let Some(rules_dir) = &self.config.rules_dir {
+            configs.extend(list_local_packs(rules_dir).into_iter().map(|name| format!("{}{}", LOCAL_PACK_PREFIX, name)));
+        }
+        if !self.config.offline {
+            configs.extend(REGISTRY_PACKS.iter().map(|pack| pack.to_string()));
+        }
+        configs
+    }
+
+    fn saved_rule_ids(&self) -> Vec<String> {
+        self.config.rule_library().list().unwrap_or_default().into_iter().map(|rule| rule.id).collect()
+    }
+
+    /// Rule IDs reported by stored scans, most recent scan first.
+    fn scanned_rule_ids(&self) -> Vec<String> {
+        self.scans
+            .list()
+            .iter()
+            .rev()
+            .flat_map(|scan| scan.results().iter().filter_map(|r| r.get("check_id")?.as_str().map(|s| s.to_string())).collect::<Vec<_>>())
+            .collect()
+    }
+
+    fn scan_ids(&self) -> Vec<String> {
+        let scans = self.scans.list();
+        let mut ids = Vec::with_capacity(scans.len() + 1);
+        if !scans.is_empty() {
+            ids.push("latest".to_string());
+        }
+        ids.extend(scans.iter().rev().map(|scan| scan.id.clone()));
+        ids
+    }
+
+    fn fingerprints(&self, scan_id: Option<&str>) -> Vec<String> {
+        let Some(scan) = self.scans.get(scan_id.unwrap_or("latest")) else { return Vec::new() };
+        scan.results().iter().filter_map(|r| r.get("fingerprint")?.as_str().map(|s| s.to_string())).collect()
+    }
+
+    async fn project_names(&self) -> Vec<String> {
+        if self.config.offline {
+            return Vec::new();
+        }
+        let Some(token) = ApiClient::token() else { return Vec::new() };
+        self.projects
+            .get(async move { Ok(ApiClient::list_projects(&token).await?.into_iter().map(|p| p.name).collect()) })
+            .await
+    }
+}
+
+/// A list fetched on first use and refreshed in the background once it is older than [`CACHE_TTL`].
+#[derive(Default)]
+struct CachedList {
+    state: Arc<Mutex<CacheState>>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    values: Vec<String>,
+    fetched_at: Option<Instant>,
+    refreshing: bool,
+}
+
+impl CachedList {
+    async fn get<F>(&self, fetch: F) -> Vec<String>
+    where
+        F: Future<Output = Result<Vec<String>>> + Send + 'static,
+    {
+        let stale = {
+            let mut state = self.state.lock().unwrap();
+            match state.fetched_at {
+                None => None,
+                Some(t) if t.elapsed() > CACHE_TTL && !state.refreshing => {
+                    state.refreshing = true;
+                    Some(state.values.clone())
+                }
+                Some(_) => return state.values.clone(),
+            }
+        };
+
+        let Some(values) = stale else {
+            return match fetch.await {
+                Ok(values) => {
+                    store(&self.state, values.clone());
+                    values
+                }
+                Err(e) => {
+                    debug!("Failed to load completion data: {:#}", e);
+                    Vec::new()
+                }
+            };
+        };
+
+        // Serve the stale list now and refresh it for the next request.
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            match fetch.await {
+                Ok(values) => store(&state, values),
+                Err(e) => {
+                    debug!("Failed to refresh completion data: {:#}", e);
+                    state.lock().unwrap().refreshing = false;
+                }
+            }
+        });
+        values
+    }
+}
+
+fn store(state: &Mutex<CacheState>, values: Vec<String>) {
+    let mut state = state.lock().unwrap();
+    state.values = values;
+    state.fetched_at = Some(Instant::now());
+    state.refreshing = false;
+}
@@ -0,0 +1,66 @@
+use clap::Args;
+use std::path::PathBuf;
+use crate::rule_library::RuleLibrary;
+
+/// Server-wide settings shared by every request handler.
+#[derive(Args, Debug, Clone, Default)]
+pub struct Config {
+    /// Enable tools that modify data in the Semgrep App (e.g. `triage_findings`)
+    #[arg(long, global = true, env = "SIDERO_ENABLE_WRITE_TOOLS")]
+    pub enable_write_tools: bool,
+
+    /// Directory of OSV advisory JSON files used by `semgrep_supply_chain_scan`
+    #[arg(long, global = true, env = "SIDERO_ADVISORY_DB")]
+    pub advisory_db: Option<PathBuf>,
+
+    /// Directory of secrets-focused Semgrep rules used by `semgrep_secrets_scan`
+    #[arg(long, global = true, env = "SIDERO_SECRETS_RULES")]
+    pub secrets_rules: Option<PathBuf>,
+
+    /// Directory of local rule packs, usable as `config: local/<name>`
+    #[arg(long, global = true, env = "SIDERO_RULES_DIR")]
+    pub rules_dir: Option<PathBuf>,
+
+    /// Directory of the persistent custom rule library [default: ~/.local/share/sidero/rules]
+    #[arg(long, global = true, env = "SIDERO_RULE_LIBRARY")]
+    pub rule_library: Option<PathBuf>,
+
+    /// Directory of user prompt templates (`<name>.toml`) [default: ~/.config/sidero/prompts]
+    #[arg(long, global = true, env = "SIDERO_PROMPTS_DIR")]
+    pub prompts_dir: Option<PathBuf>,
+
+    /// Only offer these tools (comma-separated names)
+    #[arg(long, global = true, env = "SIDERO_ENABLE_TOOLS", value_delimiter = ',')]
+    pub enable_tools: Vec<String>,
+
+    /// Never offer these tools (comma-separated names)
+    #[arg(long, global = true, env = "SIDERO_DISABLE_TOOLS", value_delimiter = ',')]
+    pub disable_tools: Vec<String>,
+
+    /// Only offer tools that change nothing (hides rule library writes and triage)
+    #[arg(long, global = true, env = "SIDERO_READ_ONLY")]
+    pub read_only: bool,
+
+    /// Keep `semgrep lsp` workers running so repeated scans with the same config skip
+    /// Semgrep's startup and rule loading
+    #[arg(long, global = true, env = "SIDERO_SEMGREP_WORKER")]
+    pub semgrep_worker: bool,
+
+    /// Scan every file again instead of reusing results for files that have not changed
+    #[arg(long, global = true, env = "SIDERO_NO_SCAN_CACHE")]
+    pub no_scan_cache: bool,
+
+    /// Also keep cached scan results in this directory, so they survive restarts
+    #[arg(long, global = true, env = "SIDERO_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Refuse anything that needs network access (registry configs, Semgrep App API)
+    #[arg(long, global = true, env = "SIDERO_OFFLINE")]
+    pub offline: bool,
+}
+
+impl Config {
+    pub(crate) fn rule_library(&self) -> RuleLibrary {
+        RuleLibrary::new(self.rule_library.clone().unwrap_or_else(RuleLibrary::default_dir))
+    }
+}
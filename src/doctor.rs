@@ -0,0 +1,249 @@
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use crate::api_client::ApiClient;
+use crate::config::Config;
+
+/// Oldest Semgrep release with everything sidero calls (`--experimental`, `--dump-ast`,
+/// `show supported-languages`).
+const MIN_SEMGREP_VERSION: (u32, u32, u32) = (1, 50, 0);
+
+/// Rule files Semgrep and sidero pick up from the workspace root.
+const WORKSPACE_CONFIG_FILES: &[&str] = &[".semgrep.yml", ".semgrep.yaml", ".semgrep", ".semgrepignore"];
+
+/// Markers in Semgrep's output that point at a broken Python installation rather than at Semgrep.
+const PYTHON_ERROR_MARKERS: &[&str] = &["ModuleNotFoundError", "ImportError", "No module named", "bad interpreter", "Traceback (most recent call last)"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn skipped(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Skipped, detail: detail.into(), fix: None }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+/// Result of `sidero doctor` and the `health_check` tool.
+#[derive(Debug, Serialize)]
+pub struct DoctorReport {
+    /// False if any check failed; warnings leave the server usable.
+    pub healthy: bool,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    /// Checks the Semgrep installation and sidero's environment. `workspace` defaults to the
+    /// current directory.
+    pub async fn run(config: &Config, workspace: Option<&Path>) -> Self {
+        let (semgrep, python) = check_semgrep().await;
+        let network = check_network(config).await;
+        let token = check_token(config, network.status).await;
+        let workspace = workspace.map(Path::to_path_buf).or_else(|| std::env::current_dir().ok()).unwrap_or_default();
+
+        let checks = vec![semgrep, python, check_temp_dir(), network, token, check_workspace(config, &workspace)];
+        Self { healthy: checks.iter().all(|c| c.status != CheckStatus::Fail), checks }
+    }
+
+    /// Plain-text rendering for the terminal.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for check in &self.checks {
+            let mark = match check.status {
+                CheckStatus::Ok => "ok  ",
+                CheckStatus::Warn => "warn",
+                CheckStatus::Fail => "FAIL",
+                CheckStatus::Skipped => "skip",
+            };
+            out.push_str(&format!("[{}] {:<16} {}\n", mark, check.name, check.detail));
+            if let Some(fix) = &check.fix {
+                out.push_str(&format!("       {:<16} fix: {}\n", "", fix));
+            }
+        }
+        out.push_str(if self.healthy { "\nsidero is ready.\n" } else { "\nSome checks failed; see the fixes above.\n" });
+        out
+    }
+}
+
+/// Runs `semgrep --version`, yielding the binary/version check and the Python environment check.
+async fn check_semgrep() -> (Check, Check) {
+    let output = match Command::new("semgrep").arg("--version").output().await {
+        Ok(output) => output,
+        Err(e) => {
+            return (
+                Check::fail("semgrep", format!("could not run semgrep: {}", e), "Install Semgrep (`pipx install semgrep` or `brew install semgrep`) and make sure it is on PATH"),
+                Check::skipped("python_env", "semgrep is not installed"),
+            );
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let python = python_env_check(&stdout, &stderr);
+    if !output.status.success() {
+        let first_line = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("no output").trim().to_string();
+        let fix = match python.status {
+            CheckStatus::Fail => "Fix the Python environment (see python_env), then run `semgrep --version` again",
+            _ => "Run `semgrep --version` to see the error; reinstall Semgrep if it persists",
+        };
+        return (Check::fail("semgrep", format!("semgrep --version failed ({}): {}", output.status, first_line), fix), python);
+    }
+
+    let version_text = stdout.trim();
+    let check = match parse_version(version_text) {
+        Some(version) if version >= MIN_SEMGREP_VERSION => Check::ok("semgrep", format!("semgrep {}", version_text)),
+        Some(_) => Check::fail(
+            "semgrep",
+            format!("semgrep {} is older than the minimum {}", version_text, format_version(MIN_SEMGREP_VERSION)),
+            "Upgrade Semgrep (`pipx upgrade semgrep`, `pip install --upgrade semgrep`, or `brew upgrade semgrep`)",
+        ),
+        None => Check::warn(
+            "semgrep",
+            format!("could not read a version from `semgrep --version`: {:?}", version_text),
+            "Make sure the `semgrep` on PATH is Semgrep and not a wrapper script",
+        ),
+    };
+    (check, python)
+}
+
+fn python_env_check(stdout: &str, stderr: &str) -> Check {
+    if let Some(line) = stderr.lines().chain(stdout.lines()).find(|l| PYTHON_ERROR_MARKERS.iter().any(|m| l.contains(m))) {
+        return Check::fail(
+            "python_env",
+            format!("semgrep's Python environment is broken: {}", line.trim()),
+            "Reinstall Semgrep into an isolated environment with `pipx install --force semgrep`",
+        );
+    }
+    for var in ["PYTHONHOME", "PYTHONPATH"] {
+        if let Some(value) = std::env::var_os(var) {
+            return Check::warn(
+                "python_env",
+                format!("{} is set ({}) and can shadow semgrep's own packages", var, PathBuf::from(value).display()),
+                format!("Unset {} in the environment sidero is started from", var),
+            );
+        }
+    }
+    Check::ok("python_env", "no Python environment problems detected")
+}
+
+fn check_temp_dir() -> Check {
+    let dir = std::env::temp_dir();
+    let written = tempfile::NamedTempFile::new().and_then(|mut file| file.write_all(b"sidero").map(|_| ()));
+    match written {
+        Ok(()) => Check::ok("temp_dir", format!("{} is writable", dir.display())),
+        Err(e) => Check::fail(
+            "temp_dir",
+            format!("cannot write to {}: {}", dir.display(), e),
+            "Custom-rule scans and AST dumps write temp files; set TMPDIR to a writable directory",
+        ),
+    }
+}
+
+async fn check_network(config: &Config) -> Check {
+    if config.offline {
+        return Check::skipped("network", "offline mode");
+    }
+    match ApiClient::check_reachable().await {
+        Ok(()) => Check::ok("network", "semgrep.dev is reachable"),
+        Err(e) => Check::fail(
+            "network",
+            format!("{:#}", e),
+            "Check your connection or proxy settings (HTTPS_PROXY), or start sidero with --offline to use only local rules",
+        ),
+    }
+}
+
+async fn check_token(config: &Config, network: CheckStatus) -> Check {
+    let Some(token) = ApiClient::token() else {
+        return Check::warn(
+            "app_token",
+            "no Semgrep App token; semgrep_findings, list_projects, get_project, and triage_findings are hidden",
+            "Set SEMGREP_APP_TOKEN or run `semgrep login`",
+        );
+    };
+    if config.offline {
+        return Check::skipped("app_token", "token present, not verified in offline mode");
+    }
+    if network != CheckStatus::Ok {
+        return Check::skipped("app_token", "token present, not verified because semgrep.dev is unreachable");
+    }
+    match ApiClient::verify_token(&token).await {
+        Ok(slug) => Check::ok("app_token", format!("token is valid for deployment '{}'", slug)),
+        Err(e) => Check::fail(
+            "app_token",
+            format!("token was rejected: {:#}", e),
+            "Create a new API token under Settings > Tokens on semgrep.dev and update SEMGREP_APP_TOKEN",
+        ),
+    }
+}
+
+/// Workspace rule files, plus every directory sidero was pointed at.
+fn check_workspace(config: &Config, workspace: &Path) -> Check {
+    if !workspace.is_dir() {
+        return Check::fail("workspace_config", format!("workspace {} is not a directory", workspace.display()), "Pass an existing directory as the workspace");
+    }
+
+    let configured = [
+        ("--rules-dir", &config.rules_dir),
+        ("--advisory-db", &config.advisory_db),
+        ("--secrets-rules", &config.secrets_rules),
+        ("--prompts-dir", &config.prompts_dir),
+    ];
+    let missing: Vec<String> = configured
+        .iter()
+        .filter_map(|(flag, dir)| dir.as_ref().filter(|d| !d.is_dir()).map(|d| format!("{} {}", flag, d.display())))
+        .collect();
+    if !missing.is_empty() {
+        return Check::fail(
+            "workspace_config",
+            format!("configured directories do not exist: {}", missing.join(", ")),
+            "Create the directories or remove the flags (and their SIDERO_* environment variables)",
+        );
+    }
+
+    let found: Vec<&str> = WORKSPACE_CONFIG_FILES.iter().copied().filter(|name| workspace.join(name).exists()).collect();
+    if found.is_empty() {
+        return Check::ok("workspace_config", format!("no Semgrep config in {}", workspace.display()));
+    }
+    Check::ok("workspace_config", format!("found {} in {}", found.join(", "), workspace.display()))
+}
+
+/// The first `major.minor.patch` in `text`.
+fn parse_version(text: &str) -> Option<(u32, u32, u32)> {
+    text.split_whitespace().find_map(|word| {
+        let mut parts = word.trim_start_matches('v').splitn(3, '.').map(|p| p.parse::<u32>().ok());
+        Some((parts.next()??, parts.next()??, parts.next()??))
+    })
+}
+
+fn format_version((major, minor, patch): (u32, u32, u32)) -> String {
+    format!("{}.{}.{}", major, minor, patch)
+}
@@ -0,0 +1,77 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use std::path::{Path, PathBuf};
+
+/// Directories that never contain first-party code worth inspecting.
+pub const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", ".venv", "venv", "__pycache__", "vendor", ".tox"];
+
+/// Recursively visits every regular file under `root`, skipping [`SKIPPED_DIRS`].
+/// A `root` that is itself a file is visited directly. Symlinks are not followed.
+pub fn walk(root: &Path, visit: &mut dyn FnMut(&Path)) {
+    if root.is_file() {
+        visit(root);
+        return;
+    }
+    walk_dir(root, visit);
+}
+
+fn walk_dir(dir: &Path, visit: &mut dyn FnMut(&Path)) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else { continue };
+        let path = entry.path();
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if !SKIPPED_DIRS.iter().any(|skip| name == *skip) {
+                walk_dir(&path, visit);
+            }
+        } else if file_type.is_file() {
+            visit(&path);
+        }
+    }
+}
+
+/// What Semgrep ignores when a project has no `.semgrepignore` of its own.
+const DEFAULT_SEMGREPIGNORE: &[&str] = &[
+    "node_modules/", "build/", "dist/", "vendor/", ".env/", ".venv/", ".tox/", "*.min.js", ".npm/", ".yarn/",
+    "test/", "tests/", "*_test.go", ".semgrep", ".semgrep_logs/",
+];
+
+/// The files `semgrep scan` would scan under `root`: everything but what `.semgrepignore`
+/// (or, without one, Semgrep's defaults) and, inside a git repository, `.gitignore` exclude.
+/// A `root` that is itself a file is its only target. Symlinks are not followed.
+pub fn semgrep_targets(root: &Path) -> Vec<PathBuf> {
+    if root.is_file() {
+        return vec![root.to_path_buf()];
+    }
+    let has_semgrepignore = root.ancestors().any(|dir| dir.join(".semgrepignore").is_file());
+    let defaults = if has_semgrepignore {
+        Gitignore::empty()
+    } else {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in DEFAULT_SEMGREPIGNORE {
+            let _ = builder.add_line(None, pattern);
+        }
+        builder.build().unwrap_or_else(|_| Gitignore::empty())
+    };
+
+    WalkBuilder::new(root)
+        .hidden(false)
+        .ignore(false)
+        .git_global(false)
+        .add_custom_ignore_filename(".semgrepignore")
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            entry.file_name() != ".git" && !defaults.matched(entry.path(), is_dir).is_ignore()
+        })
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Absolute, without `.` components, so `src/a.py` and `./src/a.py` are the same file.
+pub fn normalize(path: &Path) -> PathBuf {
+    std::path::absolute(path).map(|p| p.components().collect()).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -2,21 +2,108 @@ use anyhow::Result;
 use serde_json::{json, Value};
 use crate::protocol::*;
 use crate::semgrep_wrapper::SemgrepWrapper;
-use crate::api_client::ApiClient;
+use crate::api_client::{ApiClient, FindingCounts, ProjectSummary};
+use crate::config::Config;
+use crate::resources::{resource_templates, ResourceProvider, ResourceRoute};
+use crate::server::Extensions;
+use crate::rule_packs::RULE_SCHEMA_YAML;
+use crate::notifier::Notifier;
+use crate::prompts::{Embed, PromptCatalog};
+use crate::completion::CompletionProvider;
+use crate::client_log::ClientLog;
+use crate::scan_store::ScanStore;
+use crate::subscriptions::SubscriptionManager;
+use crate::tools::{ToolContext, ToolListWatcher, ToolRegistry};
+use crate::watch::WatchManager;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-pub struct Handler;
+/// Items per page of a `*/list` response.
+const PAGE_SIZE: usize = 50;
+
+pub struct Handler {
+    config: Config,
+    /// Runs Semgrep with this server's offline, worker, and cache settings.
+    semgrep: SemgrepWrapper,
+    scans: Arc<ScanStore>,
+    subscriptions: SubscriptionManager,
+    watches: WatchManager,
+    prompts: PromptCatalog,
+    completions: CompletionProvider,
+    client_log: ClientLog,
+    notifier: Notifier,
+    tools: ToolRegistry,
+    resource_providers: Vec<Arc<dyn ResourceProvider>>,
+    /// Announces tool list changes once the client has initialized.
+    tool_watcher: Mutex<Option<ToolListWatcher>>,
+    /// Protocol version agreed on in `initialize`; `None` until the client has initialized.
+    protocol_version: Mutex<Option<String>>,
+}
 
 impl Handler {
-    pub async fn handle_request(req: JsonRpcRequest) -> Result<Value, JsonRpcError> {
+    pub fn new(config: Config, notifier: Notifier, client_log: ClientLog, extensions: Extensions) -> Self {
+        let scans = Arc::new(ScanStore::new());
+        let semgrep = SemgrepWrapper::new(&config);
+        let subscriptions = SubscriptionManager::new(scans.clone(), semgrep.clone(), notifier.clone());
+        let watches = WatchManager::new(semgrep.clone(), notifier.clone(), client_log.clone(), subscriptions.subscribers());
+        let prompts = PromptCatalog::load(config.prompts_dir.as_deref(), extensions.prompts);
+        let completions = CompletionProvider::new(config.clone(), scans.clone());
+        Self {
+            config,
+            semgrep,
+            scans,
+            subscriptions,
+            watches,
+            prompts,
+            completions,
+            client_log,
+            notifier,
+            tools: extensions.tools,
+            resource_providers: extensions.resources,
+            tool_watcher: Mutex::new(None),
+            protocol_version: Mutex::new(None),
+        }
+    }
+
+    /// The protocol version agreed on in `initialize`, once the client has initialized.
+    pub fn protocol_version(&self) -> Option<String> {
+        self.protocol_version.lock().unwrap().clone()
+    }
+
+    pub fn handle_notification(&self, notif: JsonRpcNotification) {
+        match notif.method.as_str() {
+            "notifications/initialized" => {
+                tracing::info!("Client initialized notification received");
+                self.client_log.activate(self.notifier.clone());
+                let watcher = ToolListWatcher::spawn(self.tools.clone(), self.config.clone(), self.notifier.clone());
+                *self.tool_watcher.lock().unwrap() = Some(watcher);
+            }
+            // The transport owns the running requests and stops the cancelled one.
+            "notifications/cancelled" => tracing::debug!("Cancellation: {:?}", notif.params),
+            _ => {}
+        }
+    }
+
+    pub async fn handle_request(&self, req: JsonRpcRequest) -> Result<Value, JsonRpcError> {
+        // Only `initialize` and `ping` are allowed before the session is initialized.
+        if !matches!(req.method.as_str(), "initialize" | "ping") && self.protocol_version.lock().unwrap().is_none() {
+            return Err(JsonRpcError { code: -32600, message: format!("Server not initialized; send initialize before {}", req.method), data: None });
+        }
+
         match req.method.as_str() {
-            "initialize" => Self::handle_initialize(req.params).await,
-            "tools/list" => Self::handle_list_tools().await,
-            "tools/call" => Self::handle_call_tool(req.params).await,
-            "prompts/list" => Self::handle_list_prompts().await,
-            "prompts/get" => Self::handle_get_prompt(req.params).await,
-            "resources/list" => Self::handle_list_resources().await,
-            "resources/read" => Self::handle_read_resource(req.params).await,
-            "notifications/initialized" => Ok(json!(null)), 
+            "initialize" => self.handle_initialize(req.params).await,
+            "ping" => Ok(json!({})),
+            "tools/list" => self.handle_list_tools(req.params).await,
+            "tools/call" => self.handle_call_tool(req.params).await,
+            "prompts/list" => self.handle_list_prompts(req.params).await,
+            "prompts/get" => self.handle_get_prompt(req.params).await,
+            "resources/list" => self.handle_list_resources(req.params).await,
+            "resources/read" => self.handle_read_resource(req.params).await,
+            "resources/templates/list" => Self::handle_list_resource_templates(req.params).await,
+            "resources/subscribe" => self.handle_subscribe(req.params).await,
+            "resources/unsubscribe" => self.handle_unsubscribe(req.params).await,
+            "completion/complete" => self.handle_complete(req.params).await,
+            "logging/setLevel" => self.handle_set_level(req.params).await,
              _ => Err(JsonRpcError {
                 code: -32601,
                 message: format!("Method not found: {}", req.method),
@@ -25,14 +112,46 @@ impl Handler {
         }
     }
 
-    async fn handle_initialize(_params: Option<Value>) -> Result<Value, JsonRpcError> {
-        let version = SemgrepWrapper::get_version().await.unwrap_or_else(|_| "unknown".to_string());
+    async fn handle_initialize(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: InitializeParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
+             code: -32602, message: format!("Invalid params: {}", e), data: None
+        })?;
+        tracing::info!(
+            "Initializing for client {} {} (protocol {})",
+            params.clientInfo.name, params.clientInfo.version, params.protocolVersion
+        );
+
+        // Answer with the client's version when we speak it, otherwise with our newest.
+        let protocol_version = SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .find(|v| **v == params.protocolVersion)
+            .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0])
+            .to_string();
+        {
+            let mut negotiated = self.protocol_version.lock().unwrap();
+            if negotiated.is_some() {
+                return Err(JsonRpcError { code: -32600, message: "Server is already initialized".to_string(), data: None });
+            }
+            *negotiated = Some(protocol_version.clone());
+        }
+
+        let version = match SemgrepWrapper::get_version().await {
+            Ok(version) if !version.is_empty() => version,
+            result => {
+                tracing::warn!("Could not determine the Semgrep version ({}); run `sidero doctor` or the health_check tool for details",
+                    result.err().map(|e| format!("{:#}", e)).unwrap_or_else(|| "no output".to_string()));
+                "unknown".to_string()
+            }
+        };
         
         let result = InitializeResult {
-            protocolVersion: "2024-11-05".to_string(),
+            protocolVersion: protocol_version,
             capabilities: ServerCapabilities {
                 logging: Some(json!({})),
-                tools: Some(json!({"listChanged": false})),
+                tools: Some(json!({"listChanged": true})),
+                prompts: Some(json!({"listChanged": false})),
+                resources: Some(json!({"subscribe": true, "listChanged": false})),
+                completions: Some(json!({})),
             },
             serverInfo: ServerInfo {
                 name: "sidero".to_string(),
@@ -45,228 +164,366 @@ impl Handler {
 
     // --- Tools ---
 
-    async fn handle_list_tools() -> Result<Value, JsonRpcError> {
-        let tools = vec![
-            Tool {
-                name: "semgrep_scan".to_string(),
-                description: Some("Run a Semgrep scan on specific paths".to_string()),
-                inputSchema: json!({
-                    "type": "object",
-                    "properties": {
-                        "paths": { "type": "array", "items": { "type": "string" }, "description": "List of file paths to scan" },
-                        "config": { "type": "string", "description": "Rule configuration" }
-                    },
-                    "required": ["paths"]
-                }),
-            },
-            Tool {
-                name: "semgrep_scan_with_custom_rule".to_string(),
-                description: Some("Run a scan with a custom ad-hoc rule".to_string()),
-                inputSchema: json!({
-                    "type": "object",
-                    "properties": {
-                        "rule": { "type": "string", "description": "YAML rule content" },
-                        "code_files": { "type": "array", "items": { "type": "string" }, "description": "Files to scan" }
-                    },
-                    "required": ["rule", "code_files"]
-                }),
-            },
-            Tool {
-                name: "get_abstract_syntax_tree".to_string(),
-                description: Some("Get the AST of a code snippet".to_string()),
-                inputSchema: json!({
-                    "type": "object",
-                    "properties": {
-                        "code": { "type": "string", "description": "Code content" },
-                        "language": { "type": "string", "description": "Language of the code" }
-                    },
-                    "required": ["code", "language"]
-                }),
-            },
-             Tool {
-                name: "semgrep_findings".to_string(),
-                description: Some("Fetch Semgrep findings".to_string()),
-                 inputSchema: json!({
-                    "type": "object",
-                    "properties": {
-                        "issue_type": { "type": "string" },
-                        "status": { "type": "string" },
-                         "repos": { "type": "array", "items": { "type": "string" } },
-                         "severities": { "type": "array", "items": { "type": "string" } }
-                    },
-                    "required": []
-                }),
-            },
-            Tool {
-                name: "get_version".to_string(),
-                description: Some("Get Semgrep version".to_string()),
-                inputSchema: json!({ "type": "object", "properties": {} }),
-            },
-             Tool {
-                name: "supported_languages".to_string(),
-                description: Some("List supported languages".to_string()),
-                inputSchema: json!({ "type": "object", "properties": {} }),
-            },
-        ];
-
-        Ok(serde_json::to_value(ListToolsResult { tools }).unwrap())
+    async fn handle_list_tools(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let (tools, next_cursor) = paginate(self.tools.list(&self.config), params)?;
+        Ok(serde_json::to_value(ListToolsResult { tools, nextCursor: next_cursor }).unwrap())
     }
 
-    async fn handle_call_tool(params: Option<Value>) -> Result<Value, JsonRpcError> {
+    async fn handle_call_tool(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
         let params: CallToolParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
                 code: -32602, message: format!("Invalid params: {}", e), data: None,
         })?;
 
-        match params.name.as_str() {
-            "get_version" => {
-                let version = SemgrepWrapper::get_version().await.map_err(internal_error)?;
-                Ok(json!(CallToolResult { content: vec![Content::Text { text: version }], isError: None }))
-            }
-             "supported_languages" => {
-                let langs = SemgrepWrapper::get_supported_languages().await.map_err(internal_error)?;
-                Ok(json!(CallToolResult { content: vec![Content::Text { text: langs.join(", ") }], isError: None }))
-            }
-            "semgrep_scan" => {
-                let args = params.arguments.unwrap_or(json!({}));
-                let paths: Vec<String> = serde_json::from_value(args.get("paths").unwrap_or(&json!([])).clone()).map_err(|_| JsonRpcError {
-                     code: -32602, message: "Invalid paths".to_string(), data: None
-                })?;
-                let config = args.get("config").and_then(|v| v.as_str()).map(|s| s.to_string());
-                let result = SemgrepWrapper::scan(config, paths).await.map_err(internal_error)?;
-                Ok(json!(CallToolResult { content: vec![Content::Text { text: serde_json::to_string_pretty(&result).unwrap() }], isError: None }))
-            }
-            "semgrep_scan_with_custom_rule" => {
-                 let args = params.arguments.unwrap_or(json!({}));
-                 let rule = args.get("rule").and_then(|v| v.as_str()).ok_or(JsonRpcError { code: -32602, message: "Missing rule".to_string(), data: None })?.to_string();
-                 let files: Vec<String> = serde_json::from_value(args.get("code_files").unwrap_or(&json!([])).clone()).map_err(|_| JsonRpcError { code: -32602, message: "Invalid code_files".to_string(), data: None })?;
-                 
-                 let result = SemgrepWrapper::scan_with_custom_rule(rule, files).await.map_err(internal_error)?;
-                 Ok(json!(CallToolResult { content: vec![Content::Text { text: serde_json::to_string_pretty(&result).unwrap() }], isError: None }))
+        let tool = self.tools.get(&params.name).ok_or_else(|| JsonRpcError {
+            code: -32601, message: format!("Tool not found: {}", params.name), data: None,
+        })?;
+        if let Some(reason) = ToolRegistry::unavailable_reason(tool.as_ref(), &self.config) {
+            return Err(JsonRpcError { code: -32601, message: format!("{} is not available: {}", params.name, reason), data: None });
+        }
+
+        let ctx = ToolContext { config: &self.config, semgrep: &self.semgrep, scans: &self.scans, subscriptions: &self.subscriptions, watches: &self.watches };
+        let result = tool.call(&ctx, params.arguments.unwrap_or(json!({}))).await?;
+        Ok(json!(result))
+    }
+
+    // --- Prompts ---
+
+    async fn handle_list_prompts(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let (prompts, next_cursor) = paginate(self.prompts.list(), params)?;
+        Ok(serde_json::to_value(ListPromptsResult { prompts, nextCursor: next_cursor }).unwrap())
+    }
+
+    async fn handle_get_prompt(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: GetPromptParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
+             code: -32602, message: format!("Invalid params: {}", e), data: None
+        })?;
+
+        let template = self.prompts.get(&params.name).ok_or(JsonRpcError { code: -32601, message: "Prompt not found".to_string(), data: None })?;
+        let rendered = template.render(&params.arguments.unwrap_or_default()).map_err(|e| JsonRpcError {
+            code: -32602, message: e.to_string(), data: None,
+        })?;
+
+        let mut messages = vec![PromptMessage { role: "user".to_string(), content: Content::Text { text: rendered.text } }];
+        for embed in &rendered.embeds {
+            for resource in self.embed_resources(embed).await? {
+                messages.push(PromptMessage { role: "user".to_string(), content: Content::Resource { resource } });
             }
-            "get_abstract_syntax_tree" => {
-                let args = params.arguments.unwrap_or(json!({}));
-                let code = args.get("code").and_then(|v| v.as_str()).ok_or(JsonRpcError { code: -32602, message: "Missing code".to_string(), data: None })?.to_string();
-                let lang = args.get("language").and_then(|v| v.as_str()).ok_or(JsonRpcError { code: -32602, message: "Missing language".to_string(), data: None })?.to_string();
-                
-                let result = SemgrepWrapper::dump_ast(code, lang).await.map_err(internal_error)?;
-                Ok(json!(CallToolResult { content: vec![Content::Text { text: serde_json::to_string_pretty(&result).unwrap() }], isError: None }))
+        }
+
+        Ok(serde_json::to_value(GetPromptResult {
+            description: template.description.clone(),
+            messages,
+        }).unwrap())
+    }
 
+    /// Fetches the live context a prompt asked for, as embedded resource contents.
+    async fn embed_resources(&self, embed: &Embed) -> Result<Vec<ResourceContent>, JsonRpcError> {
+        match embed {
+            Embed::Resource { uri } => {
+                let (mime_type, text) = self.read_resource(uri).await?;
+                Ok(vec![ResourceContent { uri: uri.clone(), mimeType: Some(mime_type), text }])
             }
-            "semgrep_findings" => {
-                let token = std::env::var("SEMGREP_APP_TOKEN").map_err(|_| JsonRpcError { code: -32603, message: "SEMGREP_APP_TOKEN not set".to_string(), data: None })?;
-                let args = params.arguments.unwrap_or(json!({}));
-                let mut q = serde_json::Map::new();
-                if let Some(obj) = args.as_object() {
-                    for (k, v) in obj {
-                        if k == "repos" && v.is_array() {
-                            let s = v.as_array().unwrap().iter().filter_map(|x| x.as_str()).collect::<Vec<_>>().join(",");
-                            q.insert(k.clone(), Value::String(s));
-                        } else if !v.is_null() {
-                            q.insert(k.clone(), v.clone());
-                        }
+            Embed::Ast { code, language } => {
+                // The AST only helps; without Semgrep (or for a language it cannot parse) the
+                // prompt still works from the code alone.
+                let (mime_type, text) = match SemgrepWrapper::dump_ast(code.clone(), language.clone()).await {
+                    Ok(ast) => ("application/json", serde_json::to_string_pretty(&ast).unwrap()),
+                    Err(e) => {
+                        tracing::warn!("Could not embed the AST of the {} example: {:#}", language, e);
+                        ("text/plain", format!("The AST of the example code is not available (Semgrep could not parse it as {}: {:#}). Write the rule from the code itself.", language, e))
                     }
+                };
+                Ok(vec![ResourceContent { uri: format!("semgrep://ast/{}", language), mimeType: Some(mime_type.to_string()), text }])
+            }
+            Embed::Finding { scan_id, fingerprint, context_lines } => {
+                let invalid = |message: String| JsonRpcError { code: -32602, message, data: None };
+                let scan = self.scans.get(scan_id).ok_or_else(|| invalid(format!("Scan not found: {}", scan_id)))?;
+                let finding = scan
+                    .finding(fingerprint)
+                    .ok_or_else(|| invalid(format!("No finding with fingerprint {} in scan {}", fingerprint, scan.id)))?;
+
+                let mut contents = vec![ResourceContent {
+                    uri: format!("{}/finding/{}", scan.uri(), fingerprint),
+                    mimeType: Some("application/json".to_string()),
+                    text: serde_json::to_string_pretty(finding).unwrap(),
+                }];
+                if let Some(source) = finding_source(finding, *context_lines).await {
+                    contents.push(source);
                 }
-                let res = ApiClient::get_findings(&token, q).await.map_err(internal_error)?;
-                 Ok(json!(CallToolResult { content: vec![Content::Text { text: serde_json::to_string_pretty(&res).unwrap() }], isError: None }))
+                Ok(contents)
             }
-            _ => Err(JsonRpcError { code: -32601, message: format!("Tool not found: {}", params.name), data: None }),
         }
     }
 
-    // --- Prompts ---
+    // --- Logging ---
 
-    async fn handle_list_prompts() -> Result<Value, JsonRpcError> {
-        let prompts = vec![
-            Prompt {
-                name: "write_custom_semgrep_rule".to_string(),
-                description: Some("Helper to write a custom Semgrep rule".to_string()),
-                arguments: Some(vec![
-                    PromptArgument { name: "code".to_string(), description: Some("Code snippet".to_string()), required: Some(true) },
-                    PromptArgument { name: "language".to_string(), description: Some("Language".to_string()), required: Some(true) },
-                ])
-            }
-        ];
-        Ok(serde_json::to_value(ListPromptsResult { prompts }).unwrap())
+    async fn handle_set_level(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: SetLevelParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
+             code: -32602, message: format!("Invalid params: {}", e), data: None
+        })?;
+        self.client_log.set_level(params.level);
+        tracing::info!("Client log level set to {:?}", params.level);
+        Ok(json!({}))
     }
 
-    async fn handle_get_prompt(params: Option<Value>) -> Result<Value, JsonRpcError> {
-        let params: GetPromptParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
+    // --- Completion ---
+
+    async fn handle_complete(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: CompleteParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
              code: -32602, message: format!("Invalid params: {}", e), data: None
         })?;
-
-        if params.name == "write_custom_semgrep_rule" {
-            let args = params.arguments.unwrap_or_default();
-            let code = args.get("code").unwrap_or(&"".to_string()).clone();
-            let lang = args.get("language").unwrap_or(&"".to_string()).clone();
-
-            let prompt_text = format!(
-                "You are an expert at writing Semgrep rules.\n\nCode to analyze:\n```{}\n{}\n```\n\nLanguage: {}\n\nCreate a Semgrep rule to detect issues in this code.",
-                lang, code, lang
-            );
-
-            Ok(serde_json::to_value(GetPromptResult {
-                description: Some("Write custom rule".to_string()),
-                messages: vec![
-                    PromptMessage {
-                        role: "user".to_string(),
-                        content: Content::Text { text: prompt_text }
-                    }
-                ]
-            }).unwrap())
-        } else {
-             Err(JsonRpcError { code: -32601, message: "Prompt not found".to_string(), data: None })
+        if let CompletionReference::Prompt { name } = &params.reference {
+            if self.prompts.get(name).is_none() {
+                return Err(JsonRpcError { code: -32602, message: format!("Unknown prompt: {}", name), data: None });
+            }
         }
+        let completion = self.completions.complete(&params).await;
+        Ok(serde_json::to_value(CompleteResult { completion }).unwrap())
     }
 
     // --- Resources ---
 
-    async fn handle_list_resources() -> Result<Value, JsonRpcError> {
-        let resources = vec![
+    async fn handle_list_resources(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let mut resources = vec![
             Resource {
                 uri: "semgrep://rule/schema".to_string(),
                 name: "Semgrep Rule Schema".to_string(),
                 description: Some("JSON Schema for Semgrep Rules".to_string()),
+                mimeType: Some("application/yaml".to_string()),
+            },
+            Resource {
+                uri: "semgrep://projects".to_string(),
+                name: "Semgrep App Projects".to_string(),
+                description: Some("Projects in the Semgrep App deployment".to_string()),
                 mimeType: Some("application/json".to_string()),
-            }
+            },
+            Resource {
+                uri: "semgrep://languages".to_string(),
+                name: "Semgrep Supported Languages".to_string(),
+                description: Some("Languages supported by the installed Semgrep".to_string()),
+                mimeType: Some("application/json".to_string()),
+            },
+            Resource {
+                uri: "semgrep://version".to_string(),
+                name: "Semgrep Version".to_string(),
+                description: Some("Version of the installed Semgrep".to_string()),
+                mimeType: Some("text/plain".to_string()),
+            },
         ];
-        Ok(serde_json::to_value(ListResourcesResult { resources }).unwrap())
+
+        let scans = self.scans.list();
+        if !scans.is_empty() {
+            resources.push(Resource {
+                uri: "semgrep://scan/latest".to_string(),
+                name: "Latest Semgrep Scan".to_string(),
+                description: Some("Results of the most recent scan".to_string()),
+                mimeType: Some("application/json".to_string()),
+            });
+        }
+        for scan in scans.iter().rev() {
+            resources.push(Resource {
+                uri: scan.uri(),
+                name: format!("Semgrep Scan {}", scan.id),
+                description: Some(format!("{} findings in {}", scan.results().len(), scan.paths.join(", "))),
+                mimeType: Some("application/json".to_string()),
+            });
+        }
+        resources.extend(self.watches.resources());
+        for rule in self.config.rule_library().list().unwrap_or_default() {
+            resources.push(Resource {
+                uri: format!("semgrep://local-rule/{}", rule.id),
+                name: format!("Saved Rule {}", rule.id),
+                description: rule.description,
+                mimeType: Some("application/yaml".to_string()),
+            });
+        }
+        for provider in &self.resource_providers {
+            resources.extend(provider.list());
+        }
+        let (resources, next_cursor) = paginate(resources, params)?;
+        Ok(serde_json::to_value(ListResourcesResult { resources, nextCursor: next_cursor }).unwrap())
+    }
+
+    async fn handle_list_resource_templates(params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let (templates, next_cursor) = paginate(resource_templates(), params)?;
+        Ok(serde_json::to_value(ListResourceTemplatesResult { resourceTemplates: templates, nextCursor: next_cursor }).unwrap())
+    }
+
+    async fn handle_subscribe(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: SubscribeParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
+             code: -32602, message: format!("Invalid params: {}", e), data: None
+        })?;
+        let route = ResourceRoute::parse(&params.uri).ok_or_else(|| resource_not_found(&params.uri))?;
+        self.subscriptions.subscribe(&params.uri, &route).map_err(|_| resource_not_found(&params.uri))?;
+        Ok(json!({}))
+    }
+
+    async fn handle_unsubscribe(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params: SubscribeParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
+             code: -32602, message: format!("Invalid params: {}", e), data: None
+        })?;
+        self.subscriptions.unsubscribe(&params.uri);
+        Ok(json!({}))
     }
 
-    async fn handle_read_resource(params: Option<Value>) -> Result<Value, JsonRpcError> {
+    async fn handle_read_resource(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
         let params: ReadResourceParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|e| JsonRpcError {
              code: -32602, message: format!("Invalid params: {}", e), data: None
         })?;
 
-        let uri = params.uri.as_str();
-        let content = if uri == "semgrep://rule/schema" {
-             ApiClient::fetch_url("https://raw.githubusercontent.com/semgrep/semgrep-interfaces/refs/heads/main/rule_schema_v1.yaml").await.map_err(internal_error)?
-        } else if uri.starts_with("semgrep://rule/") && uri.ends_with("/yaml") {
-            // Extract rule ID
-             // semgrep://rule/{id}/yaml
-             let parts: Vec<&str> = uri.split('/').collect();
-             if parts.len() >= 4 {
-                 let rule_id = parts[2];
-                 ApiClient::fetch_url(&format!("https://semgrep.dev/c/r/{}", rule_id)).await.map_err(internal_error)?
-             } else {
-                 return Err(JsonRpcError { code: -32602, message: "Invalid resource URI".to_string(), data: None });
-             }
-        } else {
-             return Err(JsonRpcError { code: -32602, message: "Resource not found".to_string(), data: None });
-        };
+        let (mime_type, text) = self.read_resource(&params.uri).await?;
 
         Ok(serde_json::to_value(ReadResourceResult {
             contents: vec![
                 ResourceContent {
                     uri: params.uri,
-                    mimeType: Some("text/plain".to_string()),
-                    text: content
+                    mimeType: Some(mime_type),
+                    text,
                 }
             ]
         }).unwrap())
     }
+
+    /// Reads a `semgrep://` resource or one from a registered provider, returning its MIME type and text.
+    async fn read_resource(&self, uri: &str) -> Result<(String, String), JsonRpcError> {
+        if let Some(route) = ResourceRoute::parse(uri) {
+            let (mime_type, text) = self.read_routed(uri, route).await?;
+            return Ok((mime_type.to_string(), text));
+        }
+        for provider in &self.resource_providers {
+            if let Some(result) = provider.read(uri).await {
+                return result.map_err(internal_error);
+            }
+        }
+        Err(resource_not_found(uri))
+    }
+
+    async fn read_routed(&self, uri: &str, route: ResourceRoute) -> Result<(&'static str, String), JsonRpcError> {
+        Ok(match route {
+            ResourceRoute::RuleSchema => ("application/yaml", RULE_SCHEMA_YAML.to_string()),
+            ResourceRoute::RuleYaml { rule_id } => {
+                require_online(&self.config, "Registry rule resources")?;
+                let rule = ApiClient::fetch_url(&format!("https://semgrep.dev/c/r/{}", rule_id)).await.map_err(internal_error)?;
+                ("application/yaml", rule)
+            }
+            ResourceRoute::Projects => {
+                require_online(&self.config, "Project resources")?;
+                let token = app_token()?;
+                ("application/json", serde_json::to_string_pretty(&list_project_summaries(&token, false).await?).unwrap())
+            }
+            ResourceRoute::Project { name } => {
+                require_online(&self.config, "Project resources")?;
+                let token = app_token()?;
+                ("application/json", serde_json::to_string_pretty(&get_project_summary(&token, &name).await?).unwrap())
+            }
+            ResourceRoute::Languages => {
+                let langs = SemgrepWrapper::get_supported_languages().await.map_err(internal_error)?;
+                ("application/json", serde_json::to_string_pretty(&langs).unwrap())
+            }
+            ResourceRoute::Version => {
+                let version = SemgrepWrapper::get_version().await.map_err(internal_error)?;
+                ("text/plain", version)
+            }
+            ResourceRoute::Scan { scan_id } => {
+                let scan = self.scans.get(&scan_id).ok_or_else(|| resource_not_found(uri))?;
+                ("application/json", serde_json::to_string_pretty(&scan.to_json()).unwrap())
+            }
+            ResourceRoute::ScanFinding { scan_id, fingerprint } => {
+                let scan = self.scans.get(&scan_id).ok_or_else(|| resource_not_found(uri))?;
+                let finding = scan.finding(&fingerprint).ok_or_else(|| resource_not_found(uri))?;
+                ("application/json", serde_json::to_string_pretty(finding).unwrap())
+            }
+            ResourceRoute::LocalRule { rule_id } => {
+                let rule = self.config.rule_library().get(&rule_id).map_err(|_| resource_not_found(uri))?;
+                ("application/yaml", rule.rule)
+            }
+            ResourceRoute::Watch { watch_id } => {
+                let watch = self.watches.get(&watch_id).ok_or_else(|| resource_not_found(uri))?;
+                ("application/json", serde_json::to_string_pretty(&watch).unwrap())
+            }
+        })
+    }
+}
+
+/// Cuts one page out of a `*/list` result. Cursors are opaque to clients; here they are the offset of the next page.
+fn paginate<T>(items: Vec<T>, params: Option<Value>) -> Result<(Vec<T>, Option<String>), JsonRpcError> {
+    let invalid_cursor = || JsonRpcError { code: -32602, message: "Invalid cursor".to_string(), data: None };
+    let params: PaginatedParams = serde_json::from_value(params.unwrap_or(json!({}))).map_err(|_| invalid_cursor())?;
+    let start = match params.cursor {
+        Some(cursor) => cursor.parse::<usize>().ok().filter(|&offset| offset > 0 && offset < items.len()).ok_or_else(invalid_cursor)?,
+        None => 0,
+    };
+    let end = (start + PAGE_SIZE).min(items.len());
+    let next_cursor = (end < items.len()).then(|| end.to_string());
+    Ok((items.into_iter().skip(start).take(end - start).collect(), next_cursor))
+}
+
+/// The lines around a finding, numbered, as an embedded `file://` resource.
+async fn finding_source(finding: &Value, context_lines: usize) -> Option<ResourceContent> {
+    let path = finding.get("path")?.as_str()?;
+    let start = finding.pointer("/start/line")?.as_u64()? as usize;
+    let end = finding.pointer("/end/line").and_then(|v| v.as_u64()).map(|l| l as usize).unwrap_or(start);
+    let source = tokio::fs::read_to_string(path).await.ok()?;
+
+    let first = start.saturating_sub(context_lines).max(1);
+    let last = end + context_lines;
+    let text: String = source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(n, _)| (first..=last).contains(n))
+        .map(|(n, line)| format!("{}{:>5} | {}\n", if (start..=end).contains(&n) { ">" } else { " " }, n, line))
+        .collect();
+    let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+
+    Some(ResourceContent {
+        uri: format!("file://{}#L{}-L{}", absolute.display(), first, last.min(first + text.lines().count().saturating_sub(1))),
+        mimeType: Some("text/plain".to_string()),
+        text,
+    })
+}
+
+pub(crate) fn require_online(config: &Config, what: &str) -> Result<(), JsonRpcError> {
+    if config.offline {
+        return Err(JsonRpcError { code: -32603, message: format!("{} needs network access, but sidero is running with --offline", what), data: None });
+    }
+    Ok(())
+}
+
+fn resource_not_found(uri: &str) -> JsonRpcError {
+    JsonRpcError { code: -32002, message: format!("Resource not found: {}", uri), data: None }
+}
+
+pub(crate) fn app_token() -> Result<String, JsonRpcError> {
+    ApiClient::token().ok_or_else(|| JsonRpcError { code: -32603, message: "SEMGREP_APP_TOKEN not set".to_string(), data: None })
+}
+
+pub(crate) async fn list_project_summaries(token: &str, include_counts: bool) -> Result<Vec<ProjectSummary>, JsonRpcError> {
+    let projects = ApiClient::list_projects(token).await.map_err(internal_error)?;
+    let mut counts = if include_counts {
+        Some(ApiClient::get_open_finding_counts(token, &[]).await.map_err(internal_error)?)
+    } else {
+        None
+    };
+    let truncated = counts.as_ref().and_then(|counts| counts.values().find(|c| c.truncated).cloned());
+    Ok(projects
+        .iter()
+        .map(|project| {
+            // A project missing from a truncated sweep may still have findings past the cutoff.
+            let empty = truncated.as_ref().map(|t| FindingCounts { truncated: true, note: t.note.clone(), ..Default::default() }).unwrap_or_default();
+            let project_counts = counts.as_mut().map(|counts| counts.remove(&project.name).unwrap_or(empty));
+            ProjectSummary::new(project, project_counts)
+        })
+        .collect())
+}
+
+pub(crate) async fn get_project_summary(token: &str, name: &str) -> Result<ProjectSummary, JsonRpcError> {
+    let project = ApiClient::get_project(token, name).await.map_err(internal_error)?;
+    let mut counts = ApiClient::get_open_finding_counts(token, std::slice::from_ref(&project.name)).await.map_err(internal_error)?;
+    Ok(ProjectSummary::new(&project, Some(counts.remove(&project.name).unwrap_or_default())))
 }
 
-fn internal_error<E: std::fmt::Display>(e: E) -> JsonRpcError {
+pub(crate) fn internal_error<E: std::fmt::Display>(e: E) -> JsonRpcError {
     JsonRpcError {
         code: -32603,
         message: e.to_string(),
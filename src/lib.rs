@@ -0,0 +1,38 @@
+//! Sidero: a Semgrep MCP server.
+//!
+//! The `sidero` binary is a thin shim over this crate. Embedders build a [`Server`], add
+//! their own tools, prompts, and resources through [`ServerBuilder`], and serve it over stdio
+//! or any other line-delimited transport (see [`transport`]).
+
+pub mod protocol;
+pub mod semgrep_wrapper;
+mod semgrep_worker;
+pub mod api_client;
+pub mod handler;
+pub mod config;
+mod supply_chain;
+mod secrets;
+mod fs_walk;
+pub mod resources;
+mod scan_store;
+mod scan_cache;
+pub mod notifier;
+mod subscriptions;
+mod watch;
+mod rule_packs;
+mod rule_library;
+mod rule_languages;
+pub mod prompts;
+mod completion;
+pub mod client_log;
+pub mod tools;
+pub mod doctor;
+pub mod cli;
+pub mod server;
+pub mod transport;
+
+pub use config::Config;
+pub use handler::Handler;
+pub use semgrep_wrapper::SemgrepWrapper;
+pub use server::{Extensions, Server, ServerBuilder};
+pub use tools::{ToolContext, ToolHandler, ToolRegistry};
@@ -1,107 +1,52 @@
-mod protocol;
-mod semgrep_wrapper;
-mod api_client;
-mod handler;
-
 use anyhow::Result;
 use clap::Parser;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tracing::{error, info};
-use protocol::{JsonRpcMessage, JsonRpcResponse, JsonRpcErrorResponse, JsonRpcError};
-use handler::Handler;
-
+use sidero::cli::{self, Command};
+use sidero::client_log::ClientLog;
+use sidero::{Config, Server};
+use tracing::info;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+/// Semgrep MCP server. Runs `serve` unless another command is given.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(flatten)]
+    config: Config,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging to stderr to avoid corrupting stdout JSON-RPC
-    tracing_subscriber::fmt()
-        .with_writer(std::io::stderr)
+    let args = Args::parse();
+    let command = args.command.unwrap_or(Command::Serve);
+
+    // Initialize logging to stderr to avoid corrupting stdout JSON-RPC. The client gets its
+    // own copy of sidero's events, filtered by the level it selects with logging/setLevel.
+    // One-shot commands only log warnings so their output stays readable.
+    let stderr_level = if matches!(command, Command::Serve) { LevelFilter::INFO } else { LevelFilter::WARN };
+    let client_log = ClientLog::new();
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr).with_filter(stderr_level))
+        .with(client_log.layer())
         .init();
 
-    let _args = Args::parse();
-
-    info!("Starting semgrep-mcp-rs server...");
-
-    let stdin = tokio::io::stdin();
-    let stdout = tokio::io::stdout();
-    
-    let mut reader = BufReader::new(stdin);
-    let mut writer = stdout;
-
-    let mut line = String::new();
-
-    loop {
-        line.clear();
-        let bytes_read = reader.read_line(&mut line).await?;
-        if bytes_read == 0 {
-            break;
+    match command {
+        Command::Serve => {
+            info!("Starting semgrep-mcp-rs server...");
+            Server::builder(args.config).client_log(client_log).build().serve_stdio().await
         }
-
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
-        match serde_json::from_str::<JsonRpcMessage>(trimmed) {
-            Ok(msg) => {
-                match msg {
-                    JsonRpcMessage::Request(req) => {
-                        let id = req.id.clone();
-                        match Handler::handle_request(req).await {
-                            Ok(result) => {
-                                let response = JsonRpcMessage::Response(JsonRpcResponse {
-                                    jsonrpc: "2.0".to_string(),
-                                    id,
-                                    result,
-                                });
-                                send_message(&mut writer, &response).await?;
-                            }
-                            Err(err) => {
-                                let response = JsonRpcMessage::Error(JsonRpcErrorResponse {
-                                    jsonrpc: "2.0".to_string(),
-                                    id: Some(id),
-                                    error: err,
-                                });
-                                send_message(&mut writer, &response).await?;
-                            }
-                        }
-                    }
-                    JsonRpcMessage::Notification(notif) => {
-                        if notif.method == "notifications/initialized" {
-                            info!("Client initialized notification received");
-                        }
-                    }
-                     _ => {
-                    }
+        command => {
+            let code = match cli::run(command, args.config).await {
+                Ok(code) => code,
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    1
                 }
-            }
-            Err(e) => {
-                error!("Failed to parse JSON: {}", e);
-                 let response = JsonRpcMessage::Error(JsonRpcErrorResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: None,
-                    error: JsonRpcError {
-                        code: -32700,
-                        message: "Parse error".to_string(),
-                        data: None,
-                    },
-                });
-                send_message(&mut writer, &response).await?;
-            }
+            };
+            std::process::exit(code);
         }
     }
-
-    Ok(())
-}
-
-async fn send_message<W: AsyncWriteExt + Unpin>(writer: &mut W, msg: &JsonRpcMessage) -> Result<()> {
-    let json = serde_json::to_string(msg)?;
-    writer.write_all(json.as_bytes()).await?;
-    writer.write_all(b"\n").await?;
-    writer.flush().await?;
-    Ok(())
 }
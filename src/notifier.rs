@@ -0,0 +1,31 @@
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::protocol::{JsonRpcMessage, JsonRpcNotification};
+
+/// Handle for sending messages to the client outside of the request/response cycle.
+///
+/// Every outgoing message, responses included, goes through the same channel so the
+/// writer task is the only thing touching stdout.
+#[derive(Clone)]
+pub struct Notifier {
+    tx: UnboundedSender<JsonRpcMessage>,
+}
+
+impl Notifier {
+    pub fn new(tx: UnboundedSender<JsonRpcMessage>) -> Self {
+        Self { tx }
+    }
+
+    pub fn send(&self, msg: JsonRpcMessage) {
+        // The receiver only goes away while the server is shutting down.
+        let _ = self.tx.send(msg);
+    }
+
+    pub fn notify(&self, method: &str, params: Option<Value>) {
+        self.send(JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        }));
+    }
+}
@@ -0,0 +1,369 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use crate::protocol::{Prompt, PromptArgument};
+
+/// Prompt templates compiled into the binary. A user template with the same name replaces one of these.
+const BUILTIN_TEMPLATES: &[&str] = &[
+    include_str!("../assets/prompts/write_custom_semgrep_rule.toml"),
+    include_str!("../assets/prompts/triage_finding.toml"),
+    include_str!("../assets/prompts/fix_vulnerability.toml"),
+    include_str!("../assets/prompts/explain_rule.toml"),
+    include_str!("../assets/prompts/write_taint_rule.toml"),
+    include_str!("../assets/prompts/security_review_diff.toml"),
+];
+
+/// On-disk form of a prompt template (`<name>.toml`).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFile {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    arguments: Vec<TemplateArgument>,
+    /// At least one of these arguments must be given.
+    #[serde(default)]
+    one_of: Vec<String>,
+    template: String,
+    #[serde(default)]
+    embed: Vec<EmbedSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateArgument {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Live context attached to a rendered prompt. Fields other than `uri` name template arguments.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum EmbedSpec {
+    /// Any `semgrep://` resource; `uri` may use `{{arg}}` and is skipped when one of them is missing.
+    Resource { uri: String },
+    /// The AST of the code in argument `code`, parsed as `language`.
+    Ast { code: String, language: String },
+    /// A stored finding by fingerprint, with the source lines around it.
+    Finding {
+        fingerprint: String,
+        #[serde(default)]
+        scan_id: Option<String>,
+        #[serde(default = "default_context_lines")]
+        context_lines: usize,
+    },
+}
+
+fn default_context_lines() -> usize {
+    5
+}
+
+/// An attachment of a rendered prompt, with argument values filled in.
+#[derive(Debug, Clone)]
+pub enum Embed {
+    Resource { uri: String },
+    Ast { code: String, language: String },
+    Finding { scan_id: String, fingerprint: String, context_lines: usize },
+}
+
+#[derive(Debug)]
+pub struct RenderedPrompt {
+    pub text: String,
+    pub embeds: Vec<Embed>,
+}
+
+/// A parsed prompt template.
+///
+/// Templates use a small Mustache-like syntax: `{{arg}}` inserts an argument and
+/// `{{#arg}} ... {{/arg}}` keeps the enclosed text only when `arg` was given. A section
+/// tag alone on its line takes the whole line with it.
+#[derive(Debug)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<TemplateArgument>,
+    one_of: Vec<String>,
+    body: Vec<Node>,
+    embeds: Vec<ParsedEmbed>,
+}
+
+#[derive(Debug)]
+enum ParsedEmbed {
+    Resource { uri: Vec<Node> },
+    Ast { code: String, language: String },
+    Finding { fingerprint: String, scan_id: Option<String>, context_lines: usize },
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var(String),
+    Section(String, Vec<Node>),
+}
+
+impl PromptTemplate {
+    pub fn parse(source: &str) -> Result<Self> {
+        let file: TemplateFile = toml::from_str(source).context("Invalid prompt template")?;
+        if file.name.is_empty() {
+            anyhow::bail!("Prompt template has an empty name");
+        }
+        let body = parse_body(&file.template)?;
+        check_references(&body, &file.arguments)?;
+        let declared = |name: &String| -> Result<()> {
+            if !file.arguments.iter().any(|a| &a.name == name) {
+                anyhow::bail!("Template uses undeclared argument '{}'", name);
+            }
+            Ok(())
+        };
+        file.one_of.iter().try_for_each(declared)?;
+
+        let mut embeds = Vec::with_capacity(file.embed.len());
+        for spec in file.embed {
+            embeds.push(match spec {
+                EmbedSpec::Resource { uri } => {
+                    let uri = parse_body(&uri)?;
+                    check_references(&uri, &file.arguments)?;
+                    ParsedEmbed::Resource { uri }
+                }
+                EmbedSpec::Ast { code, language } => {
+                    declared(&code)?;
+                    declared(&language)?;
+                    ParsedEmbed::Ast { code, language }
+                }
+                EmbedSpec::Finding { fingerprint, scan_id, context_lines } => {
+                    declared(&fingerprint)?;
+                    scan_id.as_ref().map(declared).transpose()?;
+                    ParsedEmbed::Finding { fingerprint, scan_id, context_lines }
+                }
+            });
+        }
+
+        Ok(Self { name: file.name, description: file.description, arguments: file.arguments, one_of: file.one_of, body, embeds })
+    }
+
+    /// Renders the template, rejecting missing required arguments and arguments it does not declare.
+    ///
+    /// Embeds whose arguments were not given are left out.
+    pub fn render(&self, args: &HashMap<String, String>) -> Result<RenderedPrompt> {
+        for arg in &self.arguments {
+            if arg.required && !is_given(args, &arg.name) {
+                anyhow::bail!("Missing required argument '{}' for prompt '{}'", arg.name, self.name);
+            }
+        }
+        if let Some(unknown) = args.keys().find(|k| !self.arguments.iter().any(|a| &a.name == *k)) {
+            anyhow::bail!("Unknown argument '{}' for prompt '{}'", unknown, self.name);
+        }
+        if !self.one_of.is_empty() && !self.one_of.iter().any(|name| is_given(args, name)) {
+            anyhow::bail!("Prompt '{}' needs one of: {}", self.name, self.one_of.join(", "));
+        }
+
+        let mut text = String::new();
+        render_nodes(&self.body, args, &mut text);
+
+        let embeds = self
+            .embeds
+            .iter()
+            .filter_map(|embed| match embed {
+                ParsedEmbed::Resource { uri } => {
+                    if !all_given(uri, args) {
+                        return None;
+                    }
+                    let mut rendered = String::new();
+                    render_nodes(uri, args, &mut rendered);
+                    Some(Embed::Resource { uri: rendered })
+                }
+                ParsedEmbed::Ast { code, language } => Some(Embed::Ast {
+                    code: args.get(code).filter(|_| is_given(args, code))?.clone(),
+                    language: args.get(language).filter(|_| is_given(args, language))?.clone(),
+                }),
+                ParsedEmbed::Finding { fingerprint, scan_id, context_lines } => Some(Embed::Finding {
+                    fingerprint: args.get(fingerprint).filter(|_| is_given(args, fingerprint))?.trim().to_string(),
+                    scan_id: scan_id
+                        .as_ref()
+                        .filter(|name| is_given(args, name))
+                        .and_then(|name| args.get(name))
+                        .map(|id| id.trim().to_string())
+                        .unwrap_or_else(|| "latest".to_string()),
+                    context_lines: *context_lines,
+                }),
+            })
+            .collect();
+
+        Ok(RenderedPrompt { text: text.trim_end().to_string(), embeds })
+    }
+
+    pub fn to_prompt(&self) -> Prompt {
+        Prompt {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            arguments: Some(
+                self.arguments
+                    .iter()
+                    .map(|a| PromptArgument { name: a.name.clone(), description: a.description.clone(), required: Some(a.required) })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Every prompt the server offers: the built-ins, templates registered by an embedding
+/// application, and templates from the user prompts directory, each overriding the ones before.
+pub struct PromptCatalog {
+    templates: BTreeMap<String, PromptTemplate>,
+}
+
+impl PromptCatalog {
+    pub fn load(user_dir: Option<&Path>, extra: Vec<PromptTemplate>) -> Self {
+        let mut templates = BTreeMap::new();
+        for source in BUILTIN_TEMPLATES {
+            let template = PromptTemplate::parse(source).expect("built-in prompt template is invalid");
+            templates.insert(template.name.clone(), template);
+        }
+        for template in extra {
+            templates.insert(template.name.clone(), template);
+        }
+
+        let user_dir = user_dir.map(Path::to_path_buf).unwrap_or_else(Self::default_dir);
+        for path in template_files(&user_dir) {
+            match std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|s| PromptTemplate::parse(&s)) {
+                Ok(template) => {
+                    info!("Loaded prompt '{}' from {}", template.name, path.display());
+                    templates.insert(template.name.clone(), template);
+                }
+                Err(e) => warn!("Skipping prompt template {}: {:#}", path.display(), e),
+            }
+        }
+
+        Self { templates }
+    }
+
+    /// `$XDG_CONFIG_HOME/sidero/prompts`, falling back to `~/.config/sidero/prompts`.
+    pub fn default_dir() -> PathBuf {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .unwrap_or_else(std::env::temp_dir);
+        config_home.join("sidero").join("prompts")
+    }
+
+    pub fn list(&self) -> Vec<Prompt> {
+        self.templates.values().map(PromptTemplate::to_prompt).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+}
+
+fn template_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn parse_body(template: &str) -> Result<Vec<Node>> {
+    // Drop the line break after standalone section tags so optional blocks leave no blank lines.
+    let mut source = String::with_capacity(template.len());
+    for line in template.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let standalone = trimmed.starts_with("{{")
+            && trimmed.ends_with("}}")
+            && matches!(trimmed.as_bytes().get(2), Some(b'#' | b'/'))
+            && trimmed[2..].find("{{").is_none();
+        source.push_str(if standalone { trimmed } else { line });
+    }
+
+    // Stack of open sections: (name, nodes collected so far).
+    let mut stack: Vec<(Option<String>, Vec<Node>)> = vec![(None, Vec::new())];
+    let mut rest = source.as_str();
+    while let Some(open) = rest.find("{{") {
+        if open > 0 {
+            stack.last_mut().unwrap().1.push(Node::Text(rest[..open].to_string()));
+        }
+        let close = rest[open..].find("}}").map(|i| open + i).ok_or_else(|| anyhow::anyhow!("Unterminated '{{{{' in template"))?;
+        let tag = rest[open + 2..close].trim();
+        rest = &rest[close + 2..];
+
+        if let Some(name) = tag.strip_prefix('#') {
+            stack.push((Some(check_name(name)?), Vec::new()));
+        } else if let Some(name) = tag.strip_prefix('/') {
+            let (open_name, nodes) = stack.pop().unwrap();
+            match open_name {
+                Some(open_name) if open_name == name.trim() => stack.last_mut().unwrap().1.push(Node::Section(open_name, nodes)),
+                _ => anyhow::bail!("Unexpected '{{{{/{}}}}}' in template", name.trim()),
+            }
+        } else {
+            stack.last_mut().unwrap().1.push(Node::Var(check_name(tag)?));
+        }
+    }
+    if !rest.is_empty() {
+        stack.last_mut().unwrap().1.push(Node::Text(rest.to_string()));
+    }
+
+    let (open_name, nodes) = stack.pop().unwrap();
+    match open_name {
+        None => Ok(nodes),
+        Some(name) => anyhow::bail!("Section '{{{{#{}}}}}' is never closed", name),
+    }
+}
+
+fn check_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        anyhow::bail!("Invalid template tag '{}'", name);
+    }
+    Ok(name.to_string())
+}
+
+fn check_references(nodes: &[Node], arguments: &[TemplateArgument]) -> Result<()> {
+    for node in nodes {
+        let (name, children) = match node {
+            Node::Text(_) => continue,
+            Node::Var(name) => (name, None),
+            Node::Section(name, children) => (name, Some(children)),
+        };
+        if !arguments.iter().any(|a| &a.name == name) {
+            anyhow::bail!("Template uses undeclared argument '{}'", name);
+        }
+        if let Some(children) = children {
+            check_references(children, arguments)?;
+        }
+    }
+    Ok(())
+}
+
+fn is_given(args: &HashMap<String, String>, name: &str) -> bool {
+    args.get(name).is_some_and(|v| !v.trim().is_empty())
+}
+
+/// Whether every argument used in `nodes` was given.
+fn all_given(nodes: &[Node], args: &HashMap<String, String>) -> bool {
+    nodes.iter().all(|node| match node {
+        Node::Text(_) => true,
+        Node::Var(name) => is_given(args, name),
+        Node::Section(_, children) => all_given(children, args),
+    })
+}
+
+fn render_nodes(nodes: &[Node], args: &HashMap<String, String>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var(name) => out.push_str(args.get(name).map(String::as_str).unwrap_or_default()),
+            Node::Section(name, children) => {
+                if is_given(args, name) {
+                    render_nodes(children, args, out);
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,21 @@
+#![allow(non_snake_case)]
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 // JSON-RPC 2.0 Types
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Serialized untagged; incoming messages are parsed with [`JsonRpcMessage::classify`] instead,
+/// because untagged deserialization would accept malformed messages as the wrong variant.
+#[derive(Debug, Serialize, Clone)]
 #[serde(untagged)]
 pub enum JsonRpcMessage {
     Request(JsonRpcRequest),
     Response(JsonRpcResponse),
     Notification(JsonRpcNotification),
     Error(JsonRpcErrorResponse),
+    /// A JSON-RPC batch; only ever sent, since incoming batches are split up before parsing.
+    Batch(Vec<JsonRpcMessage>),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -58,8 +64,94 @@ pub enum RequestId {
     String(String),
 }
 
+/// A message that is valid JSON but not a valid JSON-RPC 2.0 message.
+#[derive(Debug)]
+pub struct InvalidMessage {
+    /// The message's id, when it has a usable one.
+    pub id: Option<RequestId>,
+    pub reason: String,
+}
+
+impl InvalidMessage {
+    fn new(id: Option<RequestId>, reason: impl Into<String>) -> Self {
+        Self { id, reason: reason.into() }
+    }
+}
+
+impl JsonRpcMessage {
+    /// Validates a single JSON-RPC 2.0 message and classifies it by the members present:
+    /// `method` with `id` is a request, `method` alone a notification, and `result` or
+    /// `error` a response.
+    pub fn classify(value: Value) -> Result<Self, InvalidMessage> {
+        let Value::Object(mut object) = value else {
+            return Err(InvalidMessage::new(None, "message must be a JSON object"));
+        };
+
+        let id = match object.remove("id") {
+            None => None,
+            Some(Value::Null) => Some(None),
+            Some(id) => match serde_json::from_value::<RequestId>(id) {
+                Ok(id) => Some(Some(id)),
+                Err(_) => return Err(InvalidMessage::new(None, "id must be a string or an integer")),
+            },
+        };
+        let recovered = id.clone().flatten();
+        let invalid = |reason: &str| Err(InvalidMessage::new(recovered.clone(), reason));
+
+        match object.remove("jsonrpc") {
+            Some(Value::String(version)) if version == "2.0" => {}
+            Some(_) => return invalid("jsonrpc must be \"2.0\""),
+            None => return invalid("missing jsonrpc member"),
+        }
+
+        let result = object.remove("result");
+        let error = object.remove("error");
+        let params = object.remove("params");
+        if matches!(params, Some(ref p) if !p.is_object() && !p.is_array()) {
+            return invalid("params must be an object or an array");
+        }
+
+        match object.remove("method") {
+            Some(_) if result.is_some() || error.is_some() => invalid("a request must not carry result or error"),
+            Some(Value::String(method)) => match id {
+                None => Ok(JsonRpcMessage::Notification(JsonRpcNotification { jsonrpc: "2.0".to_string(), method, params })),
+                Some(Some(id)) => Ok(JsonRpcMessage::Request(JsonRpcRequest { jsonrpc: "2.0".to_string(), id, method, params })),
+                Some(None) => invalid("request id must not be null"),
+            },
+            Some(_) => invalid("method must be a string"),
+            None if params.is_some() => invalid("missing method"),
+            None => match (result, error) {
+                (Some(_), Some(_)) => invalid("a response must not carry both result and error"),
+                (Some(result), None) => match id {
+                    Some(Some(id)) => Ok(JsonRpcMessage::Response(JsonRpcResponse { jsonrpc: "2.0".to_string(), id, result })),
+                    _ => invalid("response id must not be null"),
+                },
+                (None, Some(error)) => match serde_json::from_value::<JsonRpcError>(error) {
+                    Ok(error) => Ok(JsonRpcMessage::Error(JsonRpcErrorResponse { jsonrpc: "2.0".to_string(), id: recovered.clone(), error })),
+                    Err(_) => invalid("error must have an integer code and a string message"),
+                },
+                (None, None) => invalid("missing method"),
+            },
+        }
+    }
+}
+
 // MCP Specific Types
 
+/// Protocol versions this server speaks, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC batching was dropped from MCP in 2025-06-18.
+pub fn supports_batching(protocol_version: &str) -> bool {
+    protocol_version < "2025-06-18"
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PaginatedParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InitializeParams {
     pub protocolVersion: String,
@@ -86,6 +178,12 @@ pub struct ServerCapabilities {
     pub logging: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completions: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -97,6 +195,8 @@ pub struct ServerInfo {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListToolsResult {
     pub tools: Vec<Tool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nextCursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -104,6 +204,28 @@ pub struct Tool {
     pub name: String,
     pub description: Option<String>,
     pub inputSchema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+/// Hints about a tool's behavior. Clients may use them in their UI and approval
+/// decisions, but must not rely on them for safety.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify its environment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readOnlyHint: Option<bool>,
+    /// The tool may delete or overwrite data (only meaningful when not read-only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructiveHint: Option<bool>,
+    /// Repeating a call with the same arguments has no further effect.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotentHint: Option<bool>,
+    /// The tool talks to external systems (the Semgrep Registry or App).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub openWorldHint: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -125,6 +247,19 @@ pub struct CallToolResult {
 pub enum Content {
     #[serde(rename = "text")]
     Text { text: String },
+    /// A resource whose contents are included inline.
+    #[serde(rename = "resource")]
+    Resource { resource: ResourceContent },
+    /// A pointer to a resource the client can read with `resources/read`.
+    #[serde(rename = "resource_link")]
+    ResourceLink {
+        uri: String,
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mimeType: Option<String>,
+    },
 }
 
 // Prompts
@@ -132,6 +267,8 @@ pub enum Content {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListPromptsResult {
     pub prompts: Vec<Prompt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nextCursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -175,6 +312,8 @@ pub struct PromptMessage {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListResourcesResult {
     pub resources: Vec<Resource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nextCursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -186,11 +325,32 @@ pub struct Resource {
     pub mimeType: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListResourceTemplatesResult {
+    pub resourceTemplates: Vec<ResourceTemplate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nextCursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResourceTemplate {
+    pub uriTemplate: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mimeType: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ReadResourceParams {
     pub uri: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubscribeParams {
+    pub uri: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ReadResourceResult {
     pub contents: Vec<ResourceContent>,
@@ -203,3 +363,57 @@ pub struct ResourceContent {
     pub mimeType: Option<String>,
     pub text: String,
 }
+
+// Completion
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompleteParams {
+    #[serde(rename = "ref")]
+    pub reference: CompletionReference,
+    pub argument: CompletionArgument,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<CompletionContext>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CompletionReference {
+    #[serde(rename = "ref/prompt")]
+    Prompt { name: String },
+    #[serde(rename = "ref/resource")]
+    Resource { uri: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompletionArgument {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CompletionContext {
+    /// Arguments the client has already resolved.
+    #[serde(default)]
+    pub arguments: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompleteResult {
+    pub completion: Completion,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Completion {
+    pub values: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hasMore: Option<bool>,
+}
+
+// Logging
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetLevelParams {
+    pub level: crate::client_log::LogLevel,
+}
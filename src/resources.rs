@@ -0,0 +1,222 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use crate::protocol::{Resource, ResourceTemplate};
+
+/// Resources an embedding application serves next to sidero's own `semgrep://` ones.
+#[async_trait]
+pub trait ResourceProvider: Send + Sync {
+    /// Resources to include in `resources/list`.
+    fn list(&self) -> Vec<Resource>;
+
+    /// The MIME type and text of `uri`, or `None` if this provider does not serve it.
+    async fn read(&self, uri: &str) -> Option<anyhow::Result<(String, String)>>;
+}
+
+/// A parsed RFC 6570 URI template with simple (`{var}`) and reserved (`{+var}`) expressions,
+/// which is all the `semgrep://` scheme needs.
+#[derive(Debug, Clone)]
+pub struct UriTemplate {
+    template: &'static str,
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Literal(String),
+    /// A variable; `reserved` variables may span `/`.
+    Var { name: String, reserved: bool },
+}
+
+impl UriTemplate {
+    pub fn new(template: &'static str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                parts.push(TemplatePart::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..].find('}').map(|i| open + i).expect("unterminated URI template expression");
+            let expr = &rest[open + 1..close];
+            let (name, reserved) = match expr.strip_prefix('+') {
+                Some(name) => (name, true),
+                None => (expr, false),
+            };
+            parts.push(TemplatePart::Var { name: name.to_string(), reserved });
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+        Self { template, parts }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.template
+    }
+
+    /// Matches `uri` against the template, returning percent-decoded variable values.
+    pub fn match_uri(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut vars = HashMap::new();
+        if match_parts(&self.parts, uri, &mut vars) {
+            Some(vars)
+        } else {
+            None
+        }
+    }
+}
+
+fn match_parts(parts: &[TemplatePart], input: &str, vars: &mut HashMap<String, String>) -> bool {
+    match parts.split_first() {
+        None => input.is_empty(),
+        Some((TemplatePart::Literal(lit), rest)) => {
+            input.strip_prefix(lit.as_str()).is_some_and(|remaining| match_parts(rest, remaining, vars))
+        }
+        Some((TemplatePart::Var { name, reserved }, rest)) => {
+            // Try every possible split point, shortest value first; values are never empty.
+            let limit = if *reserved { input.len() } else { input.find('/').unwrap_or(input.len()) };
+            for end in (1..=limit).filter(|&i| input.is_char_boundary(i)) {
+                let Some(value) = percent_decode(&input[..end]) else { continue };
+                if match_parts(rest, &input[end..], vars) {
+                    vars.insert(name.clone(), value);
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Every dynamic `semgrep://` resource, as understood by [`ResourceRoute::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceRoute {
+    RuleSchema,
+    RuleYaml { rule_id: String },
+    Projects,
+    Project { name: String },
+    Languages,
+    Version,
+    /// A stored scan; `scan_id` may be `latest`.
+    Scan { scan_id: String },
+    ScanFinding { scan_id: String, fingerprint: String },
+    LocalRule { rule_id: String },
+    /// The live finding set of a `start_watch` watch.
+    Watch { watch_id: String },
+}
+
+struct TemplateDef {
+    template: UriTemplate,
+    name: &'static str,
+    description: &'static str,
+    mime_type: &'static str,
+    route: fn(&HashMap<String, String>) -> Option<ResourceRoute>,
+}
+
+fn template_defs() -> Vec<TemplateDef> {
+    vec![
+        TemplateDef {
+            template: UriTemplate::new("semgrep://rule/{rule_id}/yaml"),
+            name: "Semgrep Registry Rule",
+            description: "YAML definition of a Semgrep Registry rule, e.g. semgrep://rule/python.lang.security.audit.eval-detected.eval-detected/yaml",
+            mime_type: "application/yaml",
+            route: |vars| {
+                let rule_id = vars.get("rule_id")?;
+                is_valid_rule_id(rule_id).then(|| ResourceRoute::RuleYaml { rule_id: rule_id.clone() })
+            },
+        },
+        TemplateDef {
+            template: UriTemplate::new("semgrep://local-rule/{rule_id}"),
+            name: "Saved Rule",
+            description: "YAML of a rule saved in the local rule library with save_rule",
+            mime_type: "application/yaml",
+            route: |vars| {
+                let rule_id = vars.get("rule_id")?;
+                is_valid_rule_id(rule_id).then(|| ResourceRoute::LocalRule { rule_id: rule_id.clone() })
+            },
+        },
+        TemplateDef {
+            template: UriTemplate::new("semgrep://scan/{scan_id}"),
+            name: "Semgrep Scan",
+            description: "Results of a scan run through semgrep_scan or semgrep_scan_with_custom_rule; use 'latest' for the most recent",
+            mime_type: "application/json",
+            route: |vars| Some(ResourceRoute::Scan { scan_id: vars.get("scan_id")?.clone() }),
+        },
+        TemplateDef {
+            template: UriTemplate::new("semgrep://scan/{scan_id}/finding/{fingerprint}"),
+            name: "Semgrep Scan Finding",
+            description: "A single finding from a stored scan, addressed by its fingerprint",
+            mime_type: "application/json",
+            route: |vars| Some(ResourceRoute::ScanFinding {
+                scan_id: vars.get("scan_id")?.clone(),
+                fingerprint: vars.get("fingerprint")?.clone(),
+            }),
+        },
+        TemplateDef {
+            template: UriTemplate::new("semgrep://watch/{watch_id}"),
+            name: "Semgrep Watch",
+            description: "Live findings of a watch started with start_watch, updated as files change",
+            mime_type: "application/json",
+            route: |vars| Some(ResourceRoute::Watch { watch_id: vars.get("watch_id")?.clone() }),
+        },
+        TemplateDef {
+            template: UriTemplate::new("semgrep://projects/{+name}"),
+            name: "Semgrep App Project",
+            description: "A Semgrep App project with its tags, latest scan time, and open finding counts",
+            mime_type: "application/json",
+            route: |vars| Some(ResourceRoute::Project { name: vars.get("name")?.clone() }),
+        },
+    ]
+}
+
+/// Registry rule IDs are dotted paths of identifier-ish segments.
+fn is_valid_rule_id(rule_id: &str) -> bool {
+    !rule_id.is_empty()
+        && !rule_id.starts_with('.')
+        && !rule_id.ends_with('.')
+        && !rule_id.contains("..")
+        && rule_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+impl ResourceRoute {
+    pub fn parse(uri: &str) -> Option<Self> {
+        match uri {
+            "semgrep://rule/schema" => return Some(ResourceRoute::RuleSchema),
+            "semgrep://projects" => return Some(ResourceRoute::Projects),
+            "semgrep://languages" => return Some(ResourceRoute::Languages),
+            "semgrep://version" => return Some(ResourceRoute::Version),
+            _ => {}
+        }
+        template_defs()
+            .iter()
+            .find_map(|def| def.template.match_uri(uri).and_then(|vars| (def.route)(&vars)))
+    }
+}
+
+/// The templates advertised through `resources/templates/list`.
+pub fn resource_templates() -> Vec<ResourceTemplate> {
+    template_defs()
+        .into_iter()
+        .map(|def| ResourceTemplate {
+            uriTemplate: def.template.as_str().to_string(),
+            name: def.name.to_string(),
+            description: Some(def.description.to_string()),
+            mimeType: Some(def.mime_type.to_string()),
+        })
+        .collect()
+}
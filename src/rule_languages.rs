@@ -0,0 +1,133 @@
+//! Which files a set of Semgrep rules can match, read from the rules' `languages`.
+//!
+//! A persistent worker only opens files its rules cover: Semgrep publishes no diagnostics
+//! for the rest, so waiting on them would stall every scan.
+
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+use crate::fs_walk;
+
+/// A `languages:` key in YAML or JSON, with whatever follows it on the line.
+static LANGUAGES_KEY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?m)^[ \t-]*"?languages"?[ \t]*:[ \t]*(.*)$"#).unwrap());
+
+/// An item of a YAML block list.
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[ \t]*-[ \t]*(\S+)").unwrap());
+
+/// File extensions (with the dot) or file names for each language Semgrep parses.
+const LANGUAGE_FILES: &[(&[&str], &[&str])] = &[
+    (&["python", "py", "python2", "python3"], &[".py", ".pyi"]),
+    (&["javascript", "js"], &[".js", ".jsx", ".mjs", ".cjs"]),
+    (&["typescript", "ts"], &[".ts", ".tsx", ".mts", ".cts"]),
+    (&["java"], &[".java"]),
+    (&["go", "golang"], &[".go"]),
+    (&["ruby", "rb"], &[".rb"]),
+    (&["rust"], &[".rs"]),
+    (&["c"], &[".c", ".h"]),
+    (&["cpp", "c++"], &[".cpp", ".cc", ".cxx", ".hpp", ".hh", ".hxx", ".h"]),
+    (&["csharp", "c#", "cs"], &[".cs"]),
+    (&["php"], &[".php"]),
+    (&["kotlin", "kt"], &[".kt", ".kts"]),
+    (&["scala"], &[".scala"]),
+    (&["swift"], &[".swift"]),
+    (&["json"], &[".json"]),
+    (&["yaml"], &[".yaml", ".yml"]),
+    (&["terraform", "hcl", "tf"], &[".tf", ".hcl", ".tfvars"]),
+    (&["dockerfile", "docker"], &["dockerfile", ".dockerfile"]),
+    (&["bash", "sh"], &[".sh", ".bash"]),
+    (&["html"], &[".html", ".htm"]),
+    (&["lua"], &[".lua"]),
+    (&["ocaml"], &[".ml", ".mli"]),
+    (&["elixir", "ex"], &[".ex", ".exs"]),
+    (&["solidity", "sol"], &[".sol"]),
+    (&["dart"], &[".dart"]),
+    (&["r"], &[".r"]),
+    (&["julia"], &[".jl"]),
+    (&["clojure"], &[".clj", ".cljs", ".cljc", ".edn"]),
+    (&["scheme"], &[".scm", ".ss"]),
+    (&["lisp"], &[".lisp", ".cl", ".el"]),
+    (&["xml"], &[".xml"]),
+    (&["vue"], &[".vue"]),
+    (&["apex"], &[".cls"]),
+];
+
+/// Languages whose rules run on every file.
+const ANY_FILE: &[&str] = &["generic", "regex", "none", "aliengrep"];
+
+/// The files some set of rules can match.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    all: bool,
+    /// Lowercase extensions (`.py`) and file names (`dockerfile`).
+    patterns: Vec<&'static str>,
+}
+
+impl Coverage {
+    /// Coverage of the rules in a local config file or directory, or `None` if some rule's
+    /// languages cannot be read or are not known here.
+    pub fn of_config(config: &Path) -> Option<Self> {
+        let mut coverage = Self::default();
+        for file in fs_walk::semgrep_targets(config) {
+            let extension = file.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            if ["yaml", "yml", "json"].contains(&extension) {
+                coverage.add_rules(&std::fs::read_to_string(&file).ok()?)?;
+            }
+        }
+        Some(coverage)
+    }
+
+    /// Coverage of the rules in one YAML or JSON document.
+    pub fn of_rules(rules: &str) -> Option<Self> {
+        let mut coverage = Self::default();
+        coverage.add_rules(rules)?;
+        Some(coverage)
+    }
+
+    pub fn covers(&self, file: &Path) -> bool {
+        if self.all {
+            return true;
+        }
+        let name = file.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        self.patterns.iter().any(|pattern| match pattern.strip_prefix('.') {
+            Some(_) => name.ends_with(pattern),
+            None => name == *pattern || name.starts_with(&format!("{}.", pattern)),
+        })
+    }
+
+    fn add_rules(&mut self, rules: &str) -> Option<()> {
+        let lines: Vec<&str> = rules.lines().collect();
+        for found in LANGUAGES_KEY.captures_iter(rules) {
+            let value = found[1].trim();
+            let languages: Vec<String> = if let Some(flow) = value.strip_prefix('[') {
+                flow.split_once(']')?.0.split(',').map(unquote).filter(|l| !l.is_empty()).collect()
+            } else if value.is_empty() {
+                let line = rules[..found.get(0)?.start()].matches('\n').count();
+                lines[line + 1..].iter().map_while(|l| LIST_ITEM.captures(l)).map(|item| unquote(&item[1])).collect()
+            } else {
+                return None;
+            };
+            if languages.is_empty() {
+                return None;
+            }
+            for language in languages {
+                self.add_language(&language)?;
+            }
+        }
+        Some(())
+    }
+
+    fn add_language(&mut self, language: &str) -> Option<()> {
+        let language = language.to_lowercase();
+        if ANY_FILE.contains(&language.as_str()) {
+            self.all = true;
+            return Some(());
+        }
+        let (_, patterns) = LANGUAGE_FILES.iter().find(|(names, _)| names.contains(&language.as_str()))?;
+        self.patterns.extend(patterns.iter().copied());
+        Some(())
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
+}
@@ -0,0 +1,190 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use crate::scan_store::now;
+
+/// Prefix that selects a saved rule as a scan config, e.g. `local-rule/no-unwrap`.
+pub const LOCAL_RULE_PREFIX: &str = "local-rule/";
+
+const RULE_FILE: &str = "rule.yaml";
+const METADATA_FILE: &str = "metadata.json";
+const FIXTURES_DIR: &str = "fixtures";
+
+/// Metadata stored next to each saved rule.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RuleMetadata {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Unix timestamps (seconds).
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(default)]
+    pub fixtures: Vec<String>,
+}
+
+/// A test fixture: a code file, usually annotated with `ruleid:` / `ok:` comments.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Fixture {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedRule {
+    pub metadata: RuleMetadata,
+    pub rule: String,
+    pub fixtures: Vec<Fixture>,
+}
+
+/// Persistent store of custom rules, one directory per rule:
+///
+/// ```text
+/// <root>/<id>/rule.yaml
+/// <root>/<id>/metadata.json
+/// <root>/<id>/fixtures/<name>
+/// ```
+pub struct RuleLibrary {
+    root: PathBuf,
+}
+
+impl RuleLibrary {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// `$XDG_DATA_HOME/sidero/rules`, falling back to `~/.local/share/sidero/rules`.
+    pub fn default_dir() -> PathBuf {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share")))
+            .unwrap_or_else(std::env::temp_dir);
+        data_home.join("sidero").join("rules")
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn rule_dir(&self, id: &str) -> Result<PathBuf> {
+        if !is_valid_id(id) {
+            anyhow::bail!("Invalid rule id '{}': use letters, digits, '.', '-' and '_'", id);
+        }
+        Ok(self.root.join(id))
+    }
+
+    /// Path of a saved rule's YAML, suitable for `semgrep --config`.
+    pub fn rule_path(&self, id: &str) -> Result<PathBuf> {
+        let path = self.rule_dir(id)?.join(RULE_FILE);
+        if !path.is_file() {
+            anyhow::bail!("Rule '{}' not found in the rule library", id);
+        }
+        Ok(path)
+    }
+
+    pub fn save(&self, id: &str, rule: &str, description: Option<String>, tags: Vec<String>, fixtures: Vec<Fixture>, overwrite: bool) -> Result<RuleMetadata> {
+        let dir = self.rule_dir(id)?;
+        for fixture in &fixtures {
+            if !is_valid_id(&fixture.name) {
+                anyhow::bail!("Invalid fixture name '{}': use a plain file name such as 'test.py'", fixture.name);
+            }
+        }
+
+        let existing = self.read_metadata(&dir).ok();
+        if existing.is_some() && !overwrite {
+            anyhow::bail!("Rule '{}' already exists; pass overwrite: true to replace it", id);
+        }
+
+        let now = now();
+        let metadata = RuleMetadata {
+            id: id.to_string(),
+            description,
+            tags,
+            created_at: existing.map(|m| m.created_at).unwrap_or(now),
+            updated_at: now,
+            fixtures: fixtures.iter().map(|f| f.name.clone()).collect(),
+        };
+
+        let fixtures_dir = dir.join(FIXTURES_DIR);
+        if fixtures_dir.exists() {
+            std::fs::remove_dir_all(&fixtures_dir).context("Failed to clear old fixtures")?;
+        }
+        std::fs::create_dir_all(&fixtures_dir).with_context(|| format!("Failed to create {}", fixtures_dir.display()))?;
+        std::fs::write(dir.join(RULE_FILE), rule).context("Failed to write rule")?;
+        for fixture in &fixtures {
+            std::fs::write(fixtures_dir.join(&fixture.name), &fixture.content).context("Failed to write fixture")?;
+        }
+        std::fs::write(dir.join(METADATA_FILE), serde_json::to_string_pretty(&metadata)?).context("Failed to write rule metadata")?;
+
+        Ok(metadata)
+    }
+
+    pub fn list(&self) -> Result<Vec<RuleMetadata>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to read rule library"),
+        };
+        let mut rules: Vec<RuleMetadata> = entries
+            .flatten()
+            .filter(|entry| entry.path().join(RULE_FILE).is_file())
+            .filter_map(|entry| self.read_metadata(&entry.path()).ok())
+            .collect();
+        rules.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(rules)
+    }
+
+    pub fn get(&self, id: &str) -> Result<SavedRule> {
+        let rule_path = self.rule_path(id)?;
+        let dir = self.rule_dir(id)?;
+        let metadata = self.read_metadata(&dir)?;
+        let rule = std::fs::read_to_string(rule_path).context("Failed to read rule")?;
+        let fixtures = metadata
+            .fixtures
+            .iter()
+            .filter_map(|name| {
+                let content = std::fs::read_to_string(dir.join(FIXTURES_DIR).join(name)).ok()?;
+                Some(Fixture { name: name.clone(), content })
+            })
+            .collect();
+        Ok(SavedRule { metadata, rule, fixtures })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        let dir = self.rule_dir(id)?;
+        self.rule_path(id)?;
+        std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to delete {}", dir.display()))
+    }
+
+    /// Reads `metadata.json`, synthesizing it for rules dropped into the directory by hand.
+    fn read_metadata(&self, dir: &Path) -> Result<RuleMetadata> {
+        if !dir.join(RULE_FILE).is_file() {
+            anyhow::bail!("No rule in {}", dir.display());
+        }
+        match std::fs::read_to_string(dir.join(METADATA_FILE)) {
+            Ok(content) => serde_json::from_str(&content).context("Invalid rule metadata"),
+            Err(_) => {
+                let id = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                let modified = std::fs::metadata(dir.join(RULE_FILE))
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let fixtures = std::fs::read_dir(dir.join(FIXTURES_DIR))
+                    .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+                    .unwrap_or_default();
+                Ok(RuleMetadata { id, description: None, tags: Vec::new(), created_at: modified, updated_at: modified, fixtures })
+            }
+        }
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
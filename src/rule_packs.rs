@@ -0,0 +1,96 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use crate::config::Config;
+use crate::rule_library::LOCAL_RULE_PREFIX;
+
+/// A subset of the Semgrep rule schema, bundled so `semgrep://rule/schema` never needs the network.
+pub const RULE_SCHEMA_YAML: &str = include_str!("../assets/rule_schema_v1.yaml");
+
+/// Prefix that selects a pack from the local rules directory, e.g. `local/security-audit`.
+pub const LOCAL_PACK_PREFIX: &str = "local/";
+
+/// Whether Semgrep has to reach the network to load `config`.
+pub fn requires_network(config: &str) -> bool {
+    const REMOTE_PREFIXES: &[&str] = &["p/", "r/", "s/", "c/", "http://", "https://"];
+    REMOTE_PREFIXES.iter().any(|prefix| config.starts_with(prefix)) || matches!(config, "auto" | "policy" | "supply-chain")
+}
+
+/// Turns a user-supplied `config` into what is passed to `semgrep --config`.
+///
+/// `local/<name>` is resolved against the local rules directory (as `<name>/`,
+/// `<name>.yaml`, or `<name>.yml`) and `local-rule/<id>` against the rule library.
+/// In offline mode, configs that need the registry, and a missing config (which
+/// Semgrep treats as `auto`), are refused.
+pub fn resolve_config(config: Option<&str>, settings: &Config) -> Result<Option<String>> {
+    let Some(config) = config else {
+        if settings.offline {
+            anyhow::bail!("Offline mode: a config is required (Semgrep would otherwise fetch 'auto' rules). Use a local path or a local/<pack> from the rules directory.");
+        }
+        return Ok(None);
+    };
+
+    if let Some(id) = config.strip_prefix(LOCAL_RULE_PREFIX) {
+        let path = settings.rule_library().rule_path(id)?;
+        return Ok(Some(path.display().to_string()));
+    }
+
+    if let Some(name) = config.strip_prefix(LOCAL_PACK_PREFIX) {
+        let rules_dir = settings
+            .rules_dir
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("'{}' refers to a local rule pack, but no rules directory is configured (--rules-dir)", config))?;
+        return match find_local_pack(rules_dir, name) {
+            Some(path) => Ok(Some(path.display().to_string())),
+            None => {
+                let available = list_local_packs(rules_dir);
+                anyhow::bail!(
+                    "Local rule pack '{}' not found in {}. Available: {}",
+                    name,
+                    rules_dir.display(),
+                    if available.is_empty() { "(none)".to_string() } else { available.join(", ") }
+                )
+            }
+        };
+    }
+
+    if settings.offline && requires_network(config) {
+        anyhow::bail!(
+            "Offline mode: config '{}' needs the Semgrep Registry. Use a local path or a local/<pack> from the rules directory instead.",
+            config
+        );
+    }
+
+    Ok(Some(config.to_string()))
+}
+
+fn find_local_pack(rules_dir: &Path, name: &str) -> Option<PathBuf> {
+    // Pack names are plain relative names; never let them escape the rules directory.
+    if name.is_empty() || Path::new(name).components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+        return None;
+    }
+    [rules_dir.join(name), rules_dir.join(format!("{}.yaml", name)), rules_dir.join(format!("{}.yml", name))]
+        .into_iter()
+        .find(|p| p.exists())
+}
+
+/// Names of the packs in the local rules directory, usable as `local/<name>`.
+pub fn list_local_packs(rules_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(rules_dir) else { return Vec::new() };
+    let mut packs: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                return None;
+            }
+            if path.is_dir() {
+                return Some(name);
+            }
+            name.strip_suffix(".yaml").or_else(|| name.strip_suffix(".yml")).map(|s| s.to_string())
+        })
+        .collect();
+    packs.sort();
+    packs.dedup();
+    packs
+}
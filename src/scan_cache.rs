@@ -0,0 +1,285 @@
+//! Per-file scan results keyed by content hash, so unchanged files are not scanned twice.
+//!
+//! An entry's key covers everything that can change a file's findings: the Semgrep version,
+//! the scan flags, the rules' contents, and the file's path and content. Only local rules
+//! are cached, since a registry pack can change under the same name. Entries live in memory
+//! and, with a cache dir, on disk as `<key>.json`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+use crate::fs_walk;
+
+/// Files kept in memory; the oldest entries are dropped first.
+const MAX_ENTRIES: usize = 20_000;
+
+/// Top-level fields of a scan's output that describe the run rather than the files.
+const PER_RUN_FIELDS: &[&str] = &["results", "errors", "paths", "cache"];
+
+/// What the last scan of one file version produced.
+#[derive(Debug, Serialize, Deserialize)]
+struct FileEntry {
+    path: String,
+    /// False if Semgrep skipped the file (ignored, unsupported language, too large).
+    scanned: bool,
+    results: Vec<Value>,
+}
+
+#[derive(Default)]
+struct Memory {
+    entries: HashMap<String, Arc<FileEntry>>,
+    order: VecDeque<String>,
+}
+
+pub struct ScanCache {
+    memory: Mutex<Memory>,
+    /// Top-level output fields (`version` and the like) of the last run per scan key, so a
+    /// scan served entirely from the cache has the same shape as one Semgrep ran.
+    runs: Mutex<HashMap<String, Value>>,
+    dir: Option<PathBuf>,
+}
+
+impl ScanCache {
+    /// An in-memory cache, also stored under `dir` if given.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = &dir {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                warn!("Cannot create scan cache dir {}: {}", dir.display(), e);
+            }
+        }
+        Self { memory: Mutex::new(Memory::default()), runs: Mutex::new(HashMap::new()), dir }
+    }
+
+    /// Scans `paths`, reusing cached results for files whose entry under `scan_key` is
+    /// current. `run` scans `paths`: all of it when given `None`, and only the listed files
+    /// under it otherwise. Anything it reports outside those files is dropped, and a path
+    /// that does not exist is always passed on so Semgrep reports it. The output gains a
+    /// `cache` object with hit and miss counts.
+    ///
+    /// Only the files Semgrep would scan are hashed; listing, hashing, and reading and writing
+    /// the disk cache run on the blocking pool.
+    pub async fn scan<F, Fut>(self: &Arc<Self>, scan_key: &str, paths: &[String], run: F) -> Result<Value>
+    where
+        F: FnOnce(Option<Vec<String>>) -> Fut,
+        Fut: Future<Output = Result<Value>>,
+    {
+        let missing = paths.iter().any(|path| !Path::new(path).exists());
+        let (cache, key, roots) = (self.clone(), scan_key.to_string(), paths.to_vec());
+        let (hits, misses) = tokio::task::spawn_blocking(move || cache.lookup(&key, &roots)).await?;
+        debug!("Scan cache: {} hit(s), {} miss(es)", hits.len(), misses.len());
+
+        let hit_count = hits.len();
+        let miss_count = misses.len();
+        let mut output = if hits.is_empty() {
+            run(None).await?
+        } else if misses.is_empty() && !missing {
+            let mut output = self.last_run(scan_key).unwrap_or_else(|| json!({}));
+            output["results"] = json!([]);
+            output["errors"] = json!([]);
+            output["paths"] = json!({ "scanned": [] });
+            output
+        } else {
+            let mut output = run(Some(misses.iter().map(|(file, _)| file.display().to_string()).collect())).await?;
+            restrict(&mut output, &misses.iter().map(|(file, _)| fs_walk::normalize(file)).collect());
+            output
+        };
+        let (cache, key, fresh) = (self.clone(), scan_key.to_string(), output.clone());
+        tokio::task::spawn_blocking(move || cache.store(&key, &fresh, &misses)).await?;
+
+        let mut results: Vec<Value> = take_array(&mut output, "results");
+        let mut scanned: Vec<Value> = output.pointer_mut("/paths/scanned").and_then(|s| s.as_array_mut()).map(std::mem::take).unwrap_or_default();
+        let skipped = output.pointer_mut("/paths/skipped").map(Value::take);
+        for entry in hits.iter().filter(|entry| entry.scanned) {
+            results.extend(entry.results.iter().cloned());
+            scanned.push(Value::String(entry.path.clone()));
+        }
+        results.sort_by_key(|r| {
+            (
+                r.get("path").and_then(|p| p.as_str()).unwrap_or_default().to_string(),
+                r.pointer("/start/line").and_then(|l| l.as_u64()).unwrap_or(0),
+                r.pointer("/start/col").and_then(|c| c.as_u64()).unwrap_or(0),
+            )
+        });
+
+        if let Some(obj) = output.as_object_mut() {
+            obj.insert("results".to_string(), Value::Array(results));
+            let mut paths = json!({ "scanned": scanned });
+            if let Some(skipped) = skipped {
+                paths["skipped"] = skipped;
+            }
+            obj.insert("paths".to_string(), paths);
+            obj.insert("cache".to_string(), json!({ "hits": hit_count, "misses": miss_count }));
+        }
+        Ok(output)
+    }
+
+    /// Splits the files Semgrep would scan under `roots` into cache hits and misses, each
+    /// miss with its entry key.
+    fn lookup(&self, scan_key: &str, roots: &[String]) -> (Vec<Arc<FileEntry>>, Vec<(PathBuf, String)>) {
+        let mut hits = Vec::new();
+        let mut misses = Vec::new();
+        for file in roots.iter().flat_map(|root| fs_walk::semgrep_targets(Path::new(root))) {
+            let Ok(content) = std::fs::read(&file) else { continue };
+            let key = file_key(scan_key, &file, &content);
+            match self.get(&key) {
+                Some(entry) => hits.push(entry),
+                None => misses.push((file, key)),
+            }
+        }
+        (hits, misses)
+    }
+
+    /// Records what a fresh scan found in each of `files`, and the fields that describe the
+    /// run. Nothing is stored when Semgrep did not say which files it scanned or reported an
+    /// error that is not tied to a file, and files with errors are left out so they are
+    /// scanned again.
+    fn store(&self, scan_key: &str, output: &Value, files: &[(PathBuf, String)]) {
+        let Some(scanned) = output.pointer("/paths/scanned").and_then(|s| s.as_array()) else { return };
+        let errors = output.get("errors").and_then(|e| e.as_array()).cloned().unwrap_or_default();
+        if errors.iter().any(|e| e.get("path").is_none()) {
+            return;
+        }
+
+        let scanned: HashSet<PathBuf> = scanned.iter().filter_map(|p| p.as_str()).map(|p| fs_walk::normalize(Path::new(p))).collect();
+        let failed: HashSet<PathBuf> = errors.iter().filter_map(|e| e.get("path").and_then(|p| p.as_str())).map(|p| fs_walk::normalize(Path::new(p))).collect();
+        let mut results: HashMap<PathBuf, Vec<Value>> = HashMap::new();
+        for result in output.get("results").and_then(|r| r.as_array()).into_iter().flatten() {
+            if let Some(path) = result.get("path").and_then(|p| p.as_str()) {
+                results.entry(fs_walk::normalize(Path::new(path))).or_default().push(result.clone());
+            }
+        }
+
+        if let Some(fields) = output.as_object() {
+            let run: serde_json::Map<String, Value> = fields.iter().filter(|(name, _)| !PER_RUN_FIELDS.contains(&name.as_str())).map(|(name, value)| (name.clone(), value.clone())).collect();
+            self.put_run(scan_key, Value::Object(run));
+        }
+
+        for (file, key) in files {
+            let normalized = fs_walk::normalize(file);
+            if failed.contains(&normalized) {
+                continue;
+            }
+            let entry = FileEntry {
+                path: file.display().to_string(),
+                scanned: scanned.contains(&normalized),
+                results: results.remove(&normalized).unwrap_or_default(),
+            };
+            self.put(key, entry);
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<Arc<FileEntry>> {
+        if let Some(entry) = self.memory.lock().unwrap().entries.get(key) {
+            return Some(entry.clone());
+        }
+        let path = self.dir.as_ref()?.join(format!("{}.json", key));
+        let entry: FileEntry = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+        let entry = Arc::new(entry);
+        self.remember(key, entry.clone());
+        Some(entry)
+    }
+
+    fn put(&self, key: &str, entry: FileEntry) {
+        if let Some(dir) = &self.dir {
+            let written = serde_json::to_vec(&entry).map_err(anyhow::Error::from).and_then(|bytes| Ok(std::fs::write(dir.join(format!("{}.json", key)), bytes)?));
+            if let Err(e) = written {
+                warn!("Cannot write scan cache entry to {}: {:#}", dir.display(), e);
+            }
+        }
+        self.remember(key, Arc::new(entry));
+    }
+
+    fn last_run(&self, scan_key: &str) -> Option<Value> {
+        if let Some(run) = self.runs.lock().unwrap().get(scan_key) {
+            return Some(run.clone());
+        }
+        let path = self.dir.as_ref()?.join(format!("{}.run.json", rule_hash(scan_key)));
+        let run: Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+        self.runs.lock().unwrap().insert(scan_key.to_string(), run.clone());
+        Some(run)
+    }
+
+    fn put_run(&self, scan_key: &str, run: Value) {
+        if let Some(dir) = &self.dir {
+            if let Err(e) = std::fs::write(dir.join(format!("{}.run.json", rule_hash(scan_key))), run.to_string()) {
+                warn!("Cannot write scan cache entry to {}: {}", dir.display(), e);
+            }
+        }
+        self.runs.lock().unwrap().insert(scan_key.to_string(), run);
+    }
+
+    fn remember(&self, key: &str, entry: Arc<FileEntry>) {
+        let mut memory = self.memory.lock().unwrap();
+        if memory.entries.insert(key.to_string(), entry).is_none() {
+            memory.order.push_back(key.to_string());
+        }
+        while memory.order.len() > MAX_ENTRIES {
+            if let Some(oldest) = memory.order.pop_front() {
+                memory.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Hash of the contents of a local rule file or directory; `None` for a registry config,
+/// whose rules can change without its name changing.
+pub fn config_hash(config: &str) -> Option<String> {
+    let path = Path::new(config);
+    if !path.exists() {
+        return None;
+    }
+    let mut hasher = Sha256::new();
+    let mut files = Vec::new();
+    fs_walk::walk(path, &mut |file| files.push(file.to_path_buf()));
+    files.sort();
+    for file in files {
+        hasher.update(file.strip_prefix(path).unwrap_or(&file).to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(std::fs::read(&file).unwrap_or_default());
+        hasher.update([0]);
+    }
+    Some(hex(&hasher.finalize()))
+}
+
+/// Hash of an ad-hoc rule.
+pub fn rule_hash(rule: &str) -> String {
+    hex(&Sha256::digest(rule.as_bytes()))
+}
+
+fn file_key(scan_key: &str, file: &Path, content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(scan_key.as_bytes());
+    hasher.update([0]);
+    hasher.update(fs_walk::normalize(file).to_string_lossy().as_bytes());
+    hasher.update([0]);
+    hasher.update(content);
+    hex(&hasher.finalize())
+}
+
+/// Drops what a scan of the roots reported about files outside `files`, keeping errors that
+/// are not about a file.
+pub fn restrict(output: &mut Value, files: &HashSet<PathBuf>) {
+    let wanted = |path: Option<&str>| path.is_none_or(|path| files.contains(&fs_walk::normalize(Path::new(path))));
+    for pointer in ["/results", "/errors", "/paths/skipped"] {
+        if let Some(entries) = output.pointer_mut(pointer).and_then(|e| e.as_array_mut()) {
+            entries.retain(|entry| wanted(entry.get("path").and_then(|p| p.as_str())));
+        }
+    }
+    if let Some(scanned) = output.pointer_mut("/paths/scanned").and_then(|s| s.as_array_mut()) {
+        scanned.retain(|path| wanted(path.as_str()));
+    }
+}
+
+fn take_array(output: &mut Value, key: &str) -> Vec<Value> {
+    output.get_mut(key).and_then(|v| v.as_array_mut()).map(std::mem::take).unwrap_or_default()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
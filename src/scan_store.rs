@@ -0,0 +1,171 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::semgrep_wrapper::SemgrepWrapper;
+
+/// How many past scans are kept around as `semgrep://scan/{id}` resources.
+const MAX_SCANS: usize = 20;
+
+/// What a scan ran: a config (registry pack, file, ...) or an ad-hoc rule.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScanSource {
+    Config { config: Option<String> },
+    CustomRule { rule: String },
+}
+
+impl ScanSource {
+    /// Runs the scan described by this source over `paths`.
+    pub async fn run(&self, semgrep: &SemgrepWrapper, paths: Vec<String>) -> Result<Value> {
+        match self {
+            ScanSource::Config { config } => semgrep.scan(config.clone(), paths).await,
+            ScanSource::CustomRule { rule } => semgrep.scan_with_custom_rule(rule.clone(), paths).await,
+        }
+    }
+
+    /// Reruns this scan over `paths` for just the changed `files` under them.
+    pub async fn rescan(&self, semgrep: &SemgrepWrapper, paths: Vec<String>, files: Vec<String>) -> Result<Value> {
+        match self {
+            ScanSource::Config { config } => semgrep.rescan(config.clone(), paths, files).await,
+            ScanSource::CustomRule { rule } => semgrep.rescan_with_custom_rule(rule.clone(), paths, files).await,
+        }
+    }
+}
+
+/// A completed scan, kept in memory so clients can read it back as a resource.
+#[derive(Debug)]
+pub struct ScanRecord {
+    pub id: String,
+    pub source: ScanSource,
+    pub paths: Vec<String>,
+    /// Unix timestamp (seconds) of when the scan finished.
+    pub created_at: u64,
+    /// Semgrep's JSON output, with a `fingerprint` added to every result.
+    pub output: Value,
+}
+
+impl ScanRecord {
+    pub fn uri(&self) -> String {
+        format!("semgrep://scan/{}", self.id)
+    }
+
+    pub fn results(&self) -> &[Value] {
+        self.output.get("results").and_then(|r| r.as_array()).map(|r| r.as_slice()).unwrap_or_default()
+    }
+
+    pub fn finding(&self, fingerprint: &str) -> Option<&Value> {
+        self.results().iter().find(|r| r.get("fingerprint").and_then(|f| f.as_str()) == Some(fingerprint))
+    }
+
+    /// Scan metadata plus Semgrep's output, as served from `semgrep://scan/{id}`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "uri": self.uri(),
+            "source": self.source,
+            "paths": self.paths,
+            "created_at": self.created_at,
+            "results": self.results(),
+            "errors": self.output.get("errors").cloned().unwrap_or(json!([])),
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct ScanStore {
+    inner: Mutex<ScanStoreInner>,
+}
+
+#[derive(Default)]
+struct ScanStoreInner {
+    next_id: u64,
+    scans: VecDeque<Arc<ScanRecord>>,
+}
+
+impl ScanStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a finished scan, evicting the oldest one when the store is full.
+    pub fn insert(&self, source: ScanSource, paths: Vec<String>, mut output: Value) -> Arc<ScanRecord> {
+        add_fingerprints(&mut output);
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_id += 1;
+        let record = Arc::new(ScanRecord {
+            id: inner.next_id.to_string(),
+            source,
+            paths,
+            created_at: now(),
+            output,
+        });
+        inner.scans.push_back(record.clone());
+        while inner.scans.len() > MAX_SCANS {
+            inner.scans.pop_front();
+        }
+        record
+    }
+
+    /// Replaces the output of an existing scan with a fresh run, keeping its ID.
+    pub fn update(&self, id: &str, mut output: Value) -> Option<Arc<ScanRecord>> {
+        add_fingerprints(&mut output);
+
+        let mut inner = self.inner.lock().unwrap();
+        let slot = inner.scans.iter_mut().find(|s| s.id == id)?;
+        let record = Arc::new(ScanRecord {
+            id: slot.id.clone(),
+            source: slot.source.clone(),
+            paths: slot.paths.clone(),
+            created_at: now(),
+            output,
+        });
+        *slot = record.clone();
+        Some(record)
+    }
+
+    /// Looks up a scan by ID; `latest` resolves to the most recent scan.
+    pub fn get(&self, id: &str) -> Option<Arc<ScanRecord>> {
+        let inner = self.inner.lock().unwrap();
+        if id == "latest" {
+            return inner.scans.back().cloned();
+        }
+        inner.scans.iter().find(|s| s.id == id).cloned()
+    }
+
+    /// All stored scans, oldest first.
+    pub fn list(&self) -> Vec<Arc<ScanRecord>> {
+        self.inner.lock().unwrap().scans.iter().cloned().collect()
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// A stable identifier for a finding, derived from its rule and location.
+///
+/// Semgrep's own `extra.fingerprint` is only populated for logged-in users, so sidero
+/// computes its own.
+pub fn fingerprint(result: &Value) -> String {
+    let mut hasher = Sha256::new();
+    for key in ["/check_id", "/path", "/start/line", "/start/col", "/end/line", "/end/col"] {
+        hasher.update(result.pointer(key).map(|v| v.to_string()).unwrap_or_default());
+        hasher.update([0]);
+    }
+    hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn add_fingerprints(output: &mut Value) {
+    let Some(results) = output.get_mut("results").and_then(|r| r.as_array_mut()) else { return };
+    for result in results {
+        let fp = fingerprint(result);
+        if let Some(obj) = result.as_object_mut() {
+            obj.insert("fingerprint".to_string(), Value::String(fp));
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use crate::fs_walk::walk;
+use crate::semgrep_wrapper::SemgrepWrapper;
+
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// A built-in secret detector: a pattern whose first capture group (or whole match) is the secret.
+struct Detector {
+    id: &'static str,
+    name: &'static str,
+    severity: &'static str,
+    regex: Regex,
+    /// Minimum Shannon entropy the captured value must reach; filters out placeholders.
+    min_entropy: f64,
+}
+
+fn detectors() -> &'static [Detector] {
+    static DETECTORS: OnceLock<Vec<Detector>> = OnceLock::new();
+    DETECTORS.get_or_init(|| {
+        let d = |id, name, severity, pattern: &str, min_entropy| Detector {
+            id,
+            name,
+            severity,
+            regex: Regex::new(pattern).expect("built-in secret pattern must compile"),
+            min_entropy,
+        };
+        vec![
+            d("aws-access-key-id", "AWS access key ID", "ERROR", r"\b((?:AKIA|ASIA|AGPA|AIDA|AROA)[0-9A-Z]{16})\b", 0.0),
+            d("aws-secret-access-key", "AWS secret access key", "ERROR", r#"(?i)aws_?secret_?access_?key["']?\s*[:=]\s*["']?([A-Za-z0-9/+=]{40})\b"#, 3.0),
+            d("github-token", "GitHub token", "ERROR", r"\b(gh[pousr]_[A-Za-z0-9]{36,255})\b", 0.0),
+            d("github-fine-grained-pat", "GitHub fine-grained personal access token", "ERROR", r"\b(github_pat_[A-Za-z0-9_]{82})\b", 0.0),
+            d("gitlab-token", "GitLab personal access token", "ERROR", r"\b(glpat-[A-Za-z0-9_-]{20})\b", 0.0),
+            d("slack-token", "Slack token", "ERROR", r"\b(xox[abposr]-[A-Za-z0-9-]{10,})\b", 0.0),
+            d("slack-webhook", "Slack webhook URL", "WARNING", r"(https://hooks\.slack\.com/services/T[A-Za-z0-9]+/B[A-Za-z0-9]+/[A-Za-z0-9]+)", 0.0),
+            d("stripe-secret-key", "Stripe secret key", "ERROR", r"\b([rs]k_live_[0-9A-Za-z]{24,})\b", 0.0),
+            d("google-api-key", "Google API key", "ERROR", r"\b(AIza[0-9A-Za-z_-]{35})\b", 0.0),
+            d("anthropic-api-key", "Anthropic API key", "ERROR", r"\b(sk-ant-[A-Za-z0-9_-]{20,})\b", 0.0),
+            d("openai-api-key", "OpenAI API key", "ERROR", r"\b(sk-(?:proj-)?[A-Za-z0-9_-]{32,})\b", 3.5),
+            d("npm-token", "npm access token", "ERROR", r"\b(npm_[A-Za-z0-9]{36})\b", 0.0),
+            // Everything after the header on its line is key material too (e.g. a PEM with escaped newlines).
+            d("private-key", "Private key block", "ERROR", r"(-----BEGIN (?:RSA |EC |DSA |OPENSSH |PGP |ENCRYPTED )?PRIVATE KEY(?: BLOCK)?-----.*)", 0.0),
+            d("jwt", "JSON Web Token", "WARNING", r"\b(eyJ[A-Za-z0-9_-]{10,}\.eyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,})\b", 0.0),
+            d("url-credentials", "Credentials in URL", "WARNING", r"[a-z][a-z0-9+.-]*://[^/\s:@]+:([^/\s:@]{6,})@", 2.5),
+            d("generic-secret-assignment", "Hard-coded secret", "WARNING", r#"(?i)(?:password|passwd|pwd|secret|token|api_?key|access_?key|auth)["']?\s*[:=]\s*["']([^"'\s]{8,})["']"#, 3.0),
+        ]
+    })
+}
+
+/// Quoted literals long enough to be keys; scored for entropy by the high-entropy detector.
+fn candidate_literal_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"["'`]([A-Za-z0-9+/=_\-]{20,})["'`]"#).expect("literal pattern must compile"))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecretsReport {
+    pub results: Vec<Value>,
+    pub errors: Vec<Value>,
+    pub scanned_files: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SecretsOptions {
+    /// Directory of secrets-focused Semgrep rules, run in addition to the built-in detectors.
+    pub rules_dir: Option<PathBuf>,
+    pub builtin: bool,
+    /// Entropy threshold (bits per character) for the generic high-entropy string detector.
+    pub min_entropy: f64,
+}
+
+pub struct SecretsScanner;
+
+impl SecretsScanner {
+    pub async fn scan(semgrep: &SemgrepWrapper, paths: Vec<PathBuf>, options: SecretsOptions) -> Result<SecretsReport> {
+        let mut report = SecretsReport { results: Vec::new(), errors: Vec::new(), scanned_files: 0 };
+
+        if options.builtin {
+            let builtin_paths = paths.clone();
+            let min_entropy = options.min_entropy;
+            let (results, scanned) = tokio::task::spawn_blocking(move || scan_builtin(&builtin_paths, min_entropy)).await?;
+            report.results.extend(results);
+            report.scanned_files = scanned;
+        }
+
+        if let Some(rules_dir) = &options.rules_dir {
+            if !rules_dir.is_dir() {
+                anyhow::bail!("Secrets rules directory not found: {}", rules_dir.display());
+            }
+            let targets = paths.iter().map(|p| p.display().to_string()).collect();
+            let output = semgrep.scan(Some(rules_dir.display().to_string()), targets).await?;
+            if let Some(results) = output.get("results").and_then(|r| r.as_array()) {
+                report.results.extend(results.iter().cloned().map(redact_semgrep_result));
+            }
+            if let Some(errors) = output.get("errors").and_then(|e| e.as_array()) {
+                report.errors.extend(errors.iter().cloned());
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+fn scan_builtin(paths: &[PathBuf], min_entropy: f64) -> (Vec<Value>, usize) {
+    let mut results = Vec::new();
+    let mut scanned = 0;
+    for root in paths {
+        walk(root, &mut |path| {
+            if std::fs::metadata(path).map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+                return;
+            }
+            let Ok(bytes) = std::fs::read(path) else { return };
+            if bytes.contains(&0) {
+                return;
+            }
+            scanned += 1;
+            let content = String::from_utf8_lossy(&bytes);
+            scan_text(path, &content, min_entropy, &mut results);
+        });
+    }
+    (results, scanned)
+}
+
+struct Hit {
+    detector_id: &'static str,
+    name: &'static str,
+    severity: &'static str,
+    start: usize,
+    end: usize,
+    entropy: f64,
+}
+
+fn find_secrets(line: &str, min_entropy: f64) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = Vec::new();
+    let overlaps = |hits: &[Hit], start: usize, end: usize| hits.iter().any(|h| start < h.end && h.start < end);
+
+    for detector in detectors() {
+        for caps in detector.regex.captures_iter(line) {
+            let Some(m) = caps.get(1).or_else(|| caps.get(0)) else { continue };
+            let entropy = shannon_entropy(m.as_str());
+            if entropy < detector.min_entropy || overlaps(&hits, m.start(), m.end()) {
+                continue;
+            }
+            hits.push(Hit { detector_id: detector.id, name: detector.name, severity: detector.severity, start: m.start(), end: m.end(), entropy });
+        }
+    }
+
+    for caps in candidate_literal_regex().captures_iter(line) {
+        let Some(m) = caps.get(1) else { continue };
+        let value = m.as_str();
+        // Hex digests have a lower ceiling (4 bits/char), so judge them against a lower bar.
+        let threshold = if value.chars().all(|c| c.is_ascii_hexdigit()) { min_entropy - 1.0 } else { min_entropy };
+        let entropy = shannon_entropy(value);
+        let has_digit = value.chars().any(|c| c.is_ascii_digit());
+        let has_alpha = value.chars().any(|c| c.is_ascii_alphabetic());
+        if entropy >= threshold && has_digit && has_alpha && !overlaps(&hits, m.start(), m.end()) {
+            hits.push(Hit { detector_id: "high-entropy-string", name: "High-entropy string", severity: "INFO", start: m.start(), end: m.end(), entropy });
+        }
+    }
+
+    hits.sort_by_key(|h| h.start);
+    hits
+}
+
+fn scan_text(path: &Path, content: &str, min_entropy: f64, results: &mut Vec<Value>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let mut next = i + 1;
+        if line.contains("nosemgrep") || line.contains("nosecret") {
+            i = next;
+            continue;
+        }
+        let hits = find_secrets(line, min_entropy);
+        let redacted_line = redact_spans(line, hits.iter().map(|h| (h.start, h.end)));
+        for hit in &hits {
+            let secret = &line[hit.start..hit.end];
+            let mut end = json!({ "line": i + 1, "col": line[..hit.end].chars().count() + 1 });
+            // A private key's body follows its header; the finding spans the block and its
+            // lines are not scanned again on their own.
+            if hit.detector_id == "private-key" && !secret.contains("-----END") {
+                if let Some(last) = lines[i + 1..].iter().position(|l| l.contains("-----END") && l.contains("PRIVATE KEY")).map(|n| i + 1 + n) {
+                    end = json!({ "line": last + 1, "col": lines[last].chars().count() + 1 });
+                    next = next.max(last + 1);
+                }
+            }
+            results.push(json!({
+                "check_id": format!("sidero.secrets.{}", hit.detector_id),
+                "path": path.display().to_string(),
+                "start": { "line": i + 1, "col": line[..hit.start].chars().count() + 1 },
+                "end": end,
+                "extra": {
+                    "message": format!("{} detected", hit.name),
+                    "severity": hit.severity,
+                    "lines": redacted_line,
+                    "metadata": {
+                        "source": "builtin",
+                        "detector": hit.detector_id,
+                        "entropy": (hit.entropy * 100.0).round() / 100.0,
+                        "redacted_value": redact_value(secret),
+                    }
+                }
+            }));
+        }
+        i = next;
+    }
+}
+
+/// Bits of Shannon entropy per character.
+fn shannon_entropy(value: &str) -> f64 {
+    let len = value.chars().count();
+    if len == 0 {
+        return 0.0;
+    }
+    let mut counts = std::collections::HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    counts.values().fold(0.0, |acc, &n| {
+        let p = n as f64 / len as f64;
+        acc - p * p.log2()
+    })
+}
+
+/// Masks a secret entirely. The mask length is clamped so it gives away little about the
+/// value's length either.
+pub fn redact_value(secret: &str) -> String {
+    "*".repeat(secret.chars().count().clamp(4, 12))
+}
+
+fn redact_spans(line: &str, spans: impl Iterator<Item = (usize, usize)>) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut last = 0;
+    for (start, end) in spans {
+        if start < last {
+            continue;
+        }
+        out.push_str(&line[last..start]);
+        out.push_str(&redact_value(&line[start..end]));
+        last = end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+/// Replaces every piece of a Semgrep result that can carry the matched text.
+fn redact_semgrep_result(mut result: Value) -> Value {
+    let start_col = result.pointer("/start/col").and_then(|v| v.as_u64()).map(|c| c as usize);
+    let end_col = result.pointer("/end/col").and_then(|v| v.as_u64()).map(|c| c as usize);
+    let single_line = result.pointer("/start/line") == result.pointer("/end/line");
+
+    let Some(extra) = result.get_mut("extra").and_then(|e| e.as_object_mut()) else { return result };
+
+    if let Some(lines) = extra.get("lines").and_then(|l| l.as_str()).map(|s| s.to_string()) {
+        let redacted = match (single_line, start_col, end_col) {
+            (true, Some(start), Some(end)) if !lines.contains('\n') => {
+                let byte_at = |col: usize| lines.char_indices().nth(col.saturating_sub(1)).map_or(lines.len(), |(i, _)| i);
+                let (start, end) = (byte_at(start), byte_at(end));
+                let mut spans: Vec<(usize, usize)> = find_secrets(&lines, 4.0).iter().map(|h| (h.start, h.end)).collect();
+                if start < end {
+                    spans.retain(|&(s, e)| e <= start || s >= end);
+                    spans.push((start, end));
+                }
+                spans.sort();
+                redact_spans(&lines, spans.into_iter())
+            }
+            _ => "<redacted>".to_string(),
+        };
+        extra.insert("lines".to_string(), Value::String(redacted));
+    }
+
+    // Rule messages may interpolate metavariables, so scrub their raw values from the message too.
+    // Longest first, so a value that contains another is replaced whole.
+    let mut raw_values: Vec<String> = extra
+        .get("metavars")
+        .and_then(|m| m.as_object())
+        .map(|m| m.values().filter_map(|v| v.get("abstract_content")?.as_str().map(|s| s.to_string())).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    raw_values.sort_by_key(|raw| std::cmp::Reverse(raw.len()));
+    if let Some(message) = extra.get("message").and_then(|m| m.as_str()) {
+        let mut message = message.to_string();
+        for raw in &raw_values {
+            message = message.replace(raw.as_str(), &redact_value(raw));
+        }
+        let spans: Vec<(usize, usize)> = find_secrets(&message, 4.0).iter().map(|h| (h.start, h.end)).collect();
+        let message = redact_spans(&message, spans.into_iter());
+        extra.insert("message".to_string(), Value::String(message));
+    }
+
+    if let Some(metavars) = extra.get_mut("metavars").and_then(|m| m.as_object_mut()) {
+        for metavar in metavars.values_mut() {
+            if let Some(content) = metavar.get("abstract_content").and_then(|c| c.as_str()).map(redact_value) {
+                metavar["abstract_content"] = Value::String(content);
+            }
+            if let Some(obj) = metavar.as_object_mut() {
+                obj.remove("propagated_value");
+            }
+        }
+    }
+
+    extra.remove("fix");
+    extra.remove("dataflow_trace");
+    result
+}
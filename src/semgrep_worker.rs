@@ -0,0 +1,487 @@
+//! Long-lived `semgrep lsp` processes that keep a rule config loaded between scans.
+//!
+//! Each worker is started for one config. A scan opens the target files as LSP documents,
+//! waits for the diagnostics Semgrep publishes for them, and turns those back into the
+//! `results` of `semgrep scan --json`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+use tracing::{debug, info, warn};
+use crate::fs_walk;
+use crate::rule_languages::Coverage;
+
+/// Workers kept warm at once; the least recently used one is stopped to make room.
+const MAX_WORKERS: usize = 4;
+
+/// How long Semgrep may take to start and answer `initialize`.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long a scan waits for the next file's diagnostics before treating the worker as stuck.
+/// Only files the rules cover are opened, and Semgrep answers every one of those.
+const FILE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The rules a worker is started with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerConfig {
+    /// A `--config` value: a registry pack, a rule file, or a directory.
+    Config(String),
+    /// Rule YAML from `semgrep_scan_with_custom_rule`.
+    Rule(String),
+}
+
+impl WorkerConfig {
+    /// Whether the rules can be read here, which a worker needs to know what they cover.
+    /// Registry configs are only fetched by Semgrep itself.
+    pub fn is_local(&self) -> bool {
+        match self {
+            WorkerConfig::Config(config) => Path::new(config).exists(),
+            WorkerConfig::Rule(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for WorkerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerConfig::Config(config) => write!(f, "{}", config),
+            WorkerConfig::Rule(rule) => {
+                let digest = Sha256::digest(rule.as_bytes());
+                write!(f, "custom rule {}", digest[..6].iter().map(|b| format!("{:02x}", b)).collect::<String>())
+            }
+        }
+    }
+}
+
+/// Warm workers, one per config, restarted when they crash.
+#[derive(Default)]
+pub struct WorkerPool {
+    /// Least recently used first.
+    workers: tokio::sync::Mutex<Vec<Arc<Worker>>>,
+}
+
+impl WorkerPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scans `paths`, or with `only`, just those files under them, with the worker for
+    /// `config`, starting one if none is running. A worker that crashes during the scan is
+    /// restarted and the scan retried once.
+    pub async fn scan(&self, config: &WorkerConfig, paths: &[String], only: Option<&[String]>, offline: bool) -> Result<Value> {
+        let worker = self.worker(config, offline).await?;
+        match worker.scan(paths, only).await {
+            Err(e) if worker.exited() => {
+                warn!("semgrep worker for {} exited ({:#}); restarting it", config, e);
+                self.worker(config, offline).await?.scan(paths, only).await
+            }
+            result => result,
+        }
+    }
+
+    async fn worker(&self, config: &WorkerConfig, offline: bool) -> Result<Arc<Worker>> {
+        let mut workers = self.workers.lock().await;
+        if let Some(index) = workers.iter().position(|w| &w.config == config) {
+            let worker = workers.remove(index);
+            if !worker.exited() {
+                workers.push(worker.clone());
+                return Ok(worker);
+            }
+        }
+
+        let worker = Arc::new(Worker::start(config.clone(), offline).await?);
+        if workers.len() >= MAX_WORKERS {
+            let evicted = workers.remove(0);
+            debug!("Stopping semgrep worker for {}", evicted.config);
+        }
+        workers.push(worker.clone());
+        Ok(worker)
+    }
+}
+
+/// Messages from the worker that someone is waiting for.
+#[derive(Default)]
+struct Pending {
+    responses: HashMap<i64, oneshot::Sender<Result<Value>>>,
+    /// Keyed by absolute path, since servers differ in how they escape URIs.
+    diagnostics: HashMap<PathBuf, oneshot::Sender<Vec<Value>>>,
+    /// Errors and warnings the worker reported during the current scan, shaped like the
+    /// `errors` of `semgrep scan --json`.
+    errors: Vec<Value>,
+}
+
+struct Worker {
+    config: WorkerConfig,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Arc<Mutex<Pending>>,
+    exited: Arc<AtomicBool>,
+    next_id: AtomicI64,
+    /// Scans share one document namespace, so they run one at a time.
+    scan_lock: tokio::sync::Mutex<()>,
+    /// The files the rules can match; the only ones a scan opens.
+    coverage: Coverage,
+    _child: Child,
+    /// Keeps a custom rule's file alive for as long as the worker uses it.
+    _rule_file: Option<NamedTempFile>,
+}
+
+impl Worker {
+    async fn start(config: WorkerConfig, offline: bool) -> Result<Self> {
+        let coverage = match &config {
+            WorkerConfig::Config(config) => Coverage::of_config(Path::new(config)),
+            WorkerConfig::Rule(rule) => Coverage::of_rules(rule),
+        }
+        .with_context(|| format!("cannot tell which languages the rules of {} cover", config))?;
+        let (config_arg, rule_file) = match &config {
+            WorkerConfig::Config(config) => (config.clone(), None),
+            WorkerConfig::Rule(rule) => {
+                let file = NamedTempFile::new().context("Failed to create temp rule file")?;
+                std::fs::write(file.path(), rule).context("Failed to write rule content")?;
+                (file.path().display().to_string(), Some(file))
+            }
+        };
+
+        info!("Starting semgrep worker for {}", config);
+        let mut child = Command::new("semgrep")
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to start semgrep lsp")?;
+
+        let settings = json!({
+            "scan": {
+                "configuration": [config_arg],
+                "exclude": [],
+                "include": [],
+                "jobs": 1,
+                "maxMemory": 0,
+                "maxTargetBytes": 1_000_000,
+                "onlyGitDirty": false,
+                "ci": false
+            },
+            "metrics": { "enabled": !offline },
+            "doHover": false,
+            "trace": { "server": "off" }
+        });
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(child.stdin.take().context("semgrep lsp has no stdin")?));
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let exited = Arc::new(AtomicBool::new(false));
+        tokio::spawn(read_messages(child.stdout.take().context("semgrep lsp has no stdout")?, stdin.clone(), pending.clone(), exited.clone(), settings.clone()));
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!("semgrep lsp: {}", line.trim());
+                }
+            });
+        }
+
+        let worker = Self {
+            config,
+            stdin,
+            pending,
+            exited,
+            next_id: AtomicI64::new(1),
+            scan_lock: tokio::sync::Mutex::new(()),
+            coverage,
+            _child: child,
+            _rule_file: rule_file,
+        };
+
+        let root = file_uri(&std::env::current_dir().context("Failed to read the current directory")?);
+        let initialize = worker.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": root,
+                "workspaceFolders": [{ "uri": root, "name": "workspace" }],
+                "capabilities": { "workspace": { "configuration": true }, "textDocument": { "publishDiagnostics": {} } },
+                "initializationOptions": settings
+            }),
+        );
+        tokio::time::timeout(STARTUP_TIMEOUT, initialize).await.map_err(|_| anyhow!("semgrep lsp did not start within {}s", STARTUP_TIMEOUT.as_secs()))??;
+        worker.notify("initialized", json!({})).await?;
+        Ok(worker)
+    }
+
+    fn exited(&self) -> bool {
+        self.exited.load(Ordering::Relaxed)
+    }
+
+    async fn scan(&self, paths: &[String], only: Option<&[String]>) -> Result<Value> {
+        let _scan = self.scan_lock.lock().await;
+        // Drop waiters and errors left behind by an earlier scan.
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.diagnostics.clear();
+            pending.errors.clear();
+        }
+
+        // The files `semgrep scan` would scan, minus those no rule can match.
+        let (paths, coverage) = (paths.to_vec(), self.coverage.clone());
+        let only: Option<HashSet<PathBuf>> = only.map(|only| only.iter().map(|file| fs_walk::normalize(Path::new(file))).collect());
+        let files = tokio::task::spawn_blocking(move || {
+            paths
+                .iter()
+                .flat_map(|path| fs_walk::semgrep_targets(Path::new(path)))
+                .filter(|file| only.as_ref().is_none_or(|only| only.contains(&fs_walk::normalize(file))))
+                .filter(|file| coverage.covers(file))
+                // Semgrep skips binary files as well.
+                .filter_map(|file| Some((std::fs::read_to_string(&file).ok()?, file)))
+                .collect::<Vec<_>>()
+        })
+        .await?;
+
+        let mut opened = Vec::new();
+        for (text, file) in files {
+            let absolute = std::path::absolute(&file).unwrap_or_else(|_| file.clone());
+            let uri = file_uri(&absolute);
+            let (tx, rx) = oneshot::channel();
+            self.pending.lock().unwrap().diagnostics.insert(absolute, tx);
+            self.notify("textDocument/didOpen", json!({ "textDocument": { "uri": uri, "languageId": "", "version": 1, "text": text } })).await?;
+            opened.push((file, uri, text, Some(rx)));
+        }
+
+        // Semgrep answers in its own order.
+        let mut waiting: FuturesUnordered<_> = opened.iter_mut().enumerate().map(|(index, (_, _, _, rx))| {
+            let rx = rx.take().unwrap();
+            async move { (index, rx.await) }
+        }).collect();
+        let mut answered = HashMap::new();
+        loop {
+            match tokio::time::timeout(FILE_TIMEOUT, waiting.next()).await {
+                Ok(Some((index, Ok(diagnostics)))) => {
+                    answered.insert(index, diagnostics);
+                }
+                Ok(Some((_, Err(_)))) => bail!("semgrep lsp exited during the scan"),
+                Ok(None) => break,
+                Err(_) => {
+                    // Let the pool replace the worker rather than wait on it again.
+                    self.exited.store(true, Ordering::Relaxed);
+                    bail!("semgrep lsp sent no diagnostics for {}s", FILE_TIMEOUT.as_secs());
+                }
+            }
+        }
+        drop(waiting);
+
+        let mut results = Vec::new();
+        let mut scanned = Vec::new();
+        for (index, (file, uri, text, _)) in opened.into_iter().enumerate() {
+            self.notify("textDocument/didClose", json!({ "textDocument": { "uri": uri } })).await?;
+            let path = file.display().to_string();
+            let lines: Vec<&str> = text.lines().collect();
+            results.extend(answered.remove(&index).unwrap_or_default().iter().map(|d| diagnostic_result(&path, &lines, d)));
+            scanned.push(path);
+        }
+
+        let errors = std::mem::take(&mut self.pending.lock().unwrap().errors);
+        Ok(json!({ "results": results, "errors": errors, "paths": { "scanned": scanned } }))
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().responses.insert(id, tx);
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await?;
+        rx.await.map_err(|_| anyhow!("semgrep lsp exited before answering {}", method))?
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params })).await
+    }
+
+    async fn send(&self, message: &Value) -> Result<()> {
+        write_message(&mut *self.stdin.lock().await, message).await
+    }
+}
+
+/// Routes the worker's messages until it exits, answering the requests the LSP server makes
+/// of its client.
+async fn read_messages(
+    stdout: impl AsyncRead + Unpin,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Arc<Mutex<Pending>>,
+    exited: Arc<AtomicBool>,
+    settings: Value,
+) {
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let message = match read_message(&mut reader).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Unreadable message from semgrep lsp: {:#}", e);
+                break;
+            }
+        };
+
+        let method = message.get("method").and_then(|m| m.as_str());
+        let id = message.get("id").cloned();
+        match (method, id) {
+            (Some(method), Some(id)) => {
+                let result = match method {
+                    "workspace/configuration" => {
+                        let items = message.pointer("/params/items").and_then(|i| i.as_array()).map_or(1, |i| i.len());
+                        Value::Array(vec![settings.clone(); items])
+                    }
+                    _ => Value::Null,
+                };
+                let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                if write_message(&mut *stdin.lock().await, &response).await.is_err() {
+                    break;
+                }
+            }
+            (Some("textDocument/publishDiagnostics"), None) => {
+                let Some(path) = message.pointer("/params/uri").and_then(|u| u.as_str()).and_then(uri_path) else { continue };
+                let diagnostics = message.pointer("/params/diagnostics").and_then(|d| d.as_array()).cloned().unwrap_or_default();
+                if let Some(waiter) = pending.lock().unwrap().diagnostics.remove(&path) {
+                    let _ = waiter.send(diagnostics);
+                }
+            }
+            (Some(method @ ("window/showMessage" | "window/logMessage")), None) => {
+                // Only errors (1) and warnings (2); the rest is progress chatter.
+                let level = match message.pointer("/params/type").and_then(|t| t.as_u64()) {
+                    Some(1) => "error",
+                    Some(2) => "warn",
+                    _ => {
+                        debug!("semgrep lsp: {}", message.pointer("/params/message").and_then(|m| m.as_str()).unwrap_or_default());
+                        continue;
+                    }
+                };
+                let text = message.pointer("/params/message").and_then(|m| m.as_str()).unwrap_or_default();
+                debug!("semgrep lsp {}: {}", method, text);
+                pending.lock().unwrap().errors.push(json!({ "type": "SemgrepLspError", "level": level, "message": text }));
+            }
+            (Some(method), None) => debug!("semgrep lsp notification: {}", method),
+            (None, Some(id)) => {
+                let Some(id) = id.as_i64() else { continue };
+                let result = match message.get("error") {
+                    Some(error) => Err(anyhow!("semgrep lsp error: {}", error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error"))),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                if let Some(waiter) = pending.lock().unwrap().responses.remove(&id) {
+                    let _ = waiter.send(result);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    exited.store(true, Ordering::Relaxed);
+    // Dropping the waiters fails every call still waiting on this worker.
+    let mut pending = pending.lock().unwrap();
+    pending.responses.clear();
+    pending.diagnostics.clear();
+}
+
+async fn read_message(reader: &mut (impl AsyncBufReadExt + Unpin)) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>().context("Invalid Content-Length")?);
+            }
+        }
+    }
+
+    let mut body = vec![0; length.context("Message without Content-Length")?];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body).context("Invalid JSON from semgrep lsp")?))
+}
+
+async fn write_message(stdin: &mut ChildStdin, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    stdin.write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes()).await.context("Failed to write to semgrep lsp")?;
+    stdin.flush().await.context("Failed to write to semgrep lsp")
+}
+
+/// A `semgrep scan --json` result for an LSP diagnostic. LSP positions are zero-based.
+fn diagnostic_result(path: &str, lines: &[&str], diagnostic: &Value) -> Value {
+    let position = |end: &str| {
+        let line = diagnostic.pointer(&format!("/range/{}/line", end)).and_then(|l| l.as_u64()).unwrap_or(0);
+        let character = diagnostic.pointer(&format!("/range/{}/character", end)).and_then(|c| c.as_u64()).unwrap_or(0);
+        (line as usize, character + 1)
+    };
+    let (start_line, start_col) = position("start");
+    let (end_line, end_col) = position("end");
+    let matched = lines.get(start_line..=end_line.min(lines.len().saturating_sub(1))).unwrap_or_default().join("\n");
+    let severity = match diagnostic.get("severity").and_then(|s| s.as_u64()) {
+        Some(1) => "ERROR",
+        Some(2) => "WARNING",
+        _ => "INFO",
+    };
+    let check_id = match diagnostic.get("code") {
+        Some(Value::String(code)) => code.clone(),
+        Some(code) if !code.is_null() => code.to_string(),
+        _ => "unknown".to_string(),
+    };
+
+    json!({
+        "check_id": check_id,
+        "path": path,
+        "start": { "line": start_line + 1, "col": start_col },
+        "end": { "line": end_line + 1, "col": end_col },
+        "extra": {
+            "message": diagnostic.get("message").and_then(|m| m.as_str()).unwrap_or_default(),
+            "severity": severity,
+            "lines": matched,
+            // Diagnostics carry neither bindings nor Semgrep's fingerprint; these are what a
+            // logged-out `semgrep scan` reports.
+            "metavars": {},
+            "fingerprint": "requires login"
+        }
+    })
+}
+
+fn file_uri(path: &Path) -> String {
+    let escaped: String = path
+        .to_string_lossy()
+        .bytes()
+        .map(|b| if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect();
+    format!("file://{}", escaped)
+}
+
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let escaped = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut i = 0;
+    while i < escaped.len() {
+        let decoded = (escaped[i] == b'%').then(|| std::str::from_utf8(escaped.get(i + 1..i + 3)?).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok())).flatten();
+        match decoded {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(escaped[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&bytes).into_owned()))
+}
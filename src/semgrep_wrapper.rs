@@ -1,12 +1,88 @@
 use anyhow::{Context, Result};
 use tokio::process::Command;
-use tokio::io::AsyncWriteExt;
 use serde_json::Value;
 use tempfile::NamedTempFile;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{debug, warn};
+use crate::config::Config;
+use crate::fs_walk;
+use crate::scan_cache::{self, ScanCache};
+use crate::semgrep_worker::{WorkerConfig, WorkerPool};
 
-pub struct SemgrepWrapper;
+/// Most `--include` flags a partial rescan passes before scanning the targets whole.
+const MAX_INCLUDES: usize = 200;
+
+/// `semgrep --version`, read once for the scan cache key.
+static VERSION: OnceCell<String> = OnceCell::const_new();
+
+/// Runs Semgrep with one server's scan settings. Clones share the worker pool and the cache.
+#[derive(Clone, Default)]
+pub struct SemgrepWrapper {
+    /// In offline mode scans run with `--metrics=off` so Semgrep does not phone home.
+    offline: bool,
+    /// With persistent workers, scans with a config go to a long-lived `semgrep lsp` process
+    /// that keeps the config loaded, falling back to a one-off `semgrep scan` if it fails.
+    persistent: bool,
+    workers: Arc<WorkerPool>,
+    /// With the scan cache on, scans with a config reuse earlier results for files that have
+    /// not changed and only scan the rest.
+    cache: Option<Arc<ScanCache>>,
+}
 
 impl SemgrepWrapper {
+    /// A wrapper with the scan-related settings of `config`.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            offline: config.offline,
+            persistent: config.semgrep_worker,
+            workers: Arc::new(WorkerPool::new()),
+            cache: (!config.no_scan_cache).then(|| Arc::new(ScanCache::new(config.cache_dir.clone()))),
+        }
+    }
+
+    /// What besides the rules and the file decides a cached result: the Semgrep version and
+    /// the flags scans run with.
+    async fn cache_key(&self, rules_hash: &str) -> String {
+        let version = VERSION.get_or_init(|| async { Self::get_version().await.unwrap_or_default() }).await;
+        let flags = format!(
+            "--json --experimental{}{}",
+            if self.offline { " --metrics=off" } else { "" },
+            if self.persistent { " lsp" } else { "" }
+        );
+        format!("{}\0{}\0{}", version, flags, rules_hash)
+    }
+
+    /// Runs a scan on the persistent worker for `config`, or `None` when workers are off or the
+    /// worker failed and the caller should run `semgrep scan` itself.
+    async fn worker_scan(&self, config: WorkerConfig, paths: &[String], only: Option<&[String]>) -> Option<Value> {
+        if !self.persistent || !config.is_local() {
+            return None;
+        }
+        match self.workers.scan(&config, paths, only, self.offline).await {
+            Ok(output) => Some(output),
+            Err(e) => {
+                warn!("semgrep worker for {} failed, running a one-off scan instead: {:#}", config, e);
+                None
+            }
+        }
+    }
+
+    fn scan_command(&self) -> Command {
+        let mut cmd = Command::new("semgrep");
+        // A cancelled request drops the scan; the process should not outlive it.
+        cmd.arg("scan")
+           .arg("--json")
+           .arg("--experimental")
+           .kill_on_drop(true);
+        if self.offline {
+            cmd.arg("--metrics=off");
+        }
+        cmd
+    }
+
     pub async fn get_version() -> Result<String> {
         let output = Command::new("semgrep")
             .arg("--version")
@@ -35,15 +111,40 @@ impl SemgrepWrapper {
         Ok(languages)
     }
 
-    pub async fn scan(config: Option<String>, paths: Vec<String>) -> Result<Value> {
-        let mut cmd = Command::new("semgrep");
-        cmd.arg("scan")
-           .arg("--json")
-           .arg("--experimental");
-        
+    pub async fn scan(&self, config: Option<String>, paths: Vec<String>) -> Result<Value> {
+        // Without a config Semgrep picks rules from the working directory, which the cache
+        // cannot see.
+        if let (Some(rules_hash), Some(cache)) = (config.as_deref().and_then(scan_cache::config_hash), &self.cache) {
+            let key = self.cache_key(&rules_hash).await;
+            return cache.scan(&key, &paths, |only| self.run_scan(config.clone(), paths.clone(), only)).await;
+        }
+        self.run_scan(config, paths, None).await
+    }
+
+    /// Rescans just the `files` under `paths`, skipping those a scan of `paths` would skip.
+    /// Not cached: callers rescan files they know have changed.
+    pub async fn rescan(&self, config: Option<String>, paths: Vec<String>, files: Vec<String>) -> Result<Value> {
+        let wanted = files.iter().map(|file| fs_walk::normalize(Path::new(file))).collect();
+        let mut output = self.run_scan(config, paths, Some(files)).await?;
+        scan_cache::restrict(&mut output, &wanted);
+        Ok(output)
+    }
+
+    /// Scans `paths`, or with `only`, just those files under them.
+    async fn run_scan(&self, config: Option<String>, paths: Vec<String>, only: Option<Vec<String>>) -> Result<Value> {
+        // Without a config Semgrep looks for one in the working directory on every run.
+        if let Some(cfg) = &config {
+            if let Some(output) = self.worker_scan(WorkerConfig::Config(cfg.clone()), &paths, only.as_deref()).await {
+                return Ok(output);
+            }
+        }
+
+        let mut cmd = self.scan_command();
+
         if let Some(cfg) = config {
             cmd.arg("--config").arg(cfg);
         }
+        cmd.args(include_args(only.as_deref()));
 
         // Add paths
         for path in paths {
@@ -62,13 +163,34 @@ impl SemgrepWrapper {
              }
         }
 
+        log_stderr(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
         let json: Value = serde_json::from_str(&stdout).context("Failed to parse Semgrep JSON output")?;
         
         Ok(json)
     }
 
-    pub async fn scan_with_custom_rule(rule_content: String, code_files: Vec<String>) -> Result<Value> {
+    pub async fn scan_with_custom_rule(&self, rule_content: String, code_files: Vec<String>) -> Result<Value> {
+        if let Some(cache) = &self.cache {
+            let key = self.cache_key(&scan_cache::rule_hash(&rule_content)).await;
+            return cache.scan(&key, &code_files, |only| self.run_custom_rule(rule_content.clone(), code_files.clone(), only)).await;
+        }
+        self.run_custom_rule(rule_content, code_files, None).await
+    }
+
+    /// [`rescan`](Self::rescan) with a custom rule.
+    pub async fn rescan_with_custom_rule(&self, rule_content: String, code_files: Vec<String>, files: Vec<String>) -> Result<Value> {
+        let wanted = files.iter().map(|file| fs_walk::normalize(Path::new(file))).collect();
+        let mut output = self.run_custom_rule(rule_content, code_files, Some(files)).await?;
+        scan_cache::restrict(&mut output, &wanted);
+        Ok(output)
+    }
+
+    async fn run_custom_rule(&self, rule_content: String, code_files: Vec<String>, only: Option<Vec<String>>) -> Result<Value> {
+        if let Some(output) = self.worker_scan(WorkerConfig::Rule(rule_content.clone()), &code_files, only.as_deref()).await {
+            return Ok(output);
+        }
+
         let rule_file = NamedTempFile::new().context("Failed to create temp rule file")?;
         let rule_path = rule_file.path().to_str().unwrap().to_string();
         
@@ -76,12 +198,9 @@ impl SemgrepWrapper {
         // For simplicity and since NamedTempFile is sync, we use std::fs
         std::fs::write(&rule_path, rule_content).context("Failed to write rule content")?;
 
-        let mut cmd = Command::new("semgrep");
-        cmd.arg("scan")
-           .arg("--json")
-           .arg("--experimental")
-           .arg("--config")
-           .arg(&rule_path);
+        let mut cmd = self.scan_command();
+        cmd.arg("--config").arg(&rule_path);
+        cmd.args(include_args(only.as_deref()));
 
         for path in code_files {
             cmd.arg(path);
@@ -89,13 +208,12 @@ impl SemgrepWrapper {
 
         let output = cmd.output().await.context("Failed to execute semgrep scan")?;
 
-        if !output.status.success() {
-             if output.stdout.is_empty() {
-                 let stderr = String::from_utf8_lossy(&output.stderr);
-                 anyhow::bail!("Semgrep failed: {}", stderr);
-             }
+        if !output.status.success() && output.stdout.is_empty() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Semgrep failed: {}", stderr);
         }
 
+        log_stderr(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
         let json: Value = serde_json::from_str(&stdout).context("Failed to parse Semgrep JSON output")?;
         
@@ -114,6 +232,7 @@ impl SemgrepWrapper {
             .arg("--lang")
             .arg(language)
             .arg(&code_path)
+            .kill_on_drop(true)
             .output()
             .await
             .context("Failed to execute semgrep --dump-ast")?;
@@ -128,3 +247,31 @@ impl SemgrepWrapper {
         Ok(json)
     }
 }
+
+/// `--include` flags that narrow a scan of the original targets to `only`. Matching by file
+/// name keeps the command line short and leaves Semgrep's ignore rules in force; files that
+/// share a name are scanned too, and the cache drops their results. Past
+/// [`MAX_INCLUDES`] names the targets are scanned whole.
+fn include_args(only: Option<&[String]>) -> Vec<String> {
+    let names: BTreeSet<String> = only
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|file| Path::new(file).file_name())
+        .map(|name| name.to_string_lossy().chars().map(|c| if "*?[".contains(c) { format!("[{}]", c) } else { c.to_string() }).collect())
+        .collect();
+    if names.len() > MAX_INCLUDES {
+        return Vec::new();
+    }
+    names.into_iter().flat_map(|name| ["--include".to_string(), name]).collect()
+}
+
+/// Surfaces what Semgrep printed to stderr on a successful run: warnings at `warn`, the rest at `debug`.
+fn log_stderr(stderr: &[u8]) {
+    for line in String::from_utf8_lossy(stderr).lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if line.to_ascii_lowercase().contains("warn") {
+            warn!("semgrep: {}", line);
+        } else {
+            debug!("semgrep: {}", line);
+        }
+    }
+}
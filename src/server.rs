@@ -0,0 +1,90 @@
+use anyhow::Result;
+use std::sync::Arc;
+use crate::client_log::ClientLog;
+use crate::config::Config;
+use crate::handler::Handler;
+use crate::notifier::Notifier;
+use crate::prompts::PromptTemplate;
+use crate::resources::ResourceProvider;
+use crate::tools::{ToolHandler, ToolRegistry};
+use crate::transport;
+
+/// Tools, prompts, and resources added on top of sidero's own.
+pub struct Extensions {
+    pub tools: ToolRegistry,
+    pub prompts: Vec<PromptTemplate>,
+    pub resources: Vec<Arc<dyn ResourceProvider>>,
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Self { tools: ToolRegistry::builtin(), prompts: Vec::new(), resources: Vec::new() }
+    }
+}
+
+/// A configured sidero MCP server, ready to serve a session over a transport.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let server = sidero::Server::builder(sidero::Config::default()).build();
+/// server.serve_stdio().await
+/// # }
+/// ```
+pub struct Server {
+    config: Config,
+    client_log: ClientLog,
+    extensions: Extensions,
+}
+
+impl Server {
+    pub fn builder(config: Config) -> ServerBuilder {
+        ServerBuilder { config, client_log: None, extensions: Extensions::default() }
+    }
+
+    /// Serves MCP over stdin/stdout until stdin closes or the process is signalled.
+    pub async fn serve_stdio(self) -> Result<()> {
+        transport::serve_stdio(self).await
+    }
+
+    /// Builds the session's handler; the returned log handle must be shut down when the session ends.
+    pub(crate) fn into_handler(self, notifier: Notifier) -> (Handler, ClientLog) {
+        let handler = Handler::new(self.config, notifier, self.client_log.clone(), self.extensions);
+        (handler, self.client_log)
+    }
+}
+
+pub struct ServerBuilder {
+    config: Config,
+    client_log: Option<ClientLog>,
+    extensions: Extensions,
+}
+
+impl ServerBuilder {
+    /// Adds a tool, replacing the built-in tool of the same name if there is one.
+    pub fn tool(mut self, tool: impl ToolHandler + 'static) -> Self {
+        self.extensions.tools.register(tool);
+        self
+    }
+
+    /// Adds a prompt. Templates in the user prompts directory still override it.
+    pub fn prompt(mut self, template: PromptTemplate) -> Self {
+        self.extensions.prompts.push(template);
+        self
+    }
+
+    pub fn resources(mut self, provider: impl ResourceProvider + 'static) -> Self {
+        self.extensions.resources.push(Arc::new(provider));
+        self
+    }
+
+    /// Forwards log events to the client through `client_log`, whose layer the caller
+    /// installs in its `tracing` subscriber. Without one, nothing is forwarded.
+    pub fn client_log(mut self, client_log: ClientLog) -> Self {
+        self.client_log = Some(client_log);
+        self
+    }
+
+    pub fn build(self) -> Server {
+        Server { config: self.config, client_log: self.client_log.unwrap_or_default(), extensions: self.extensions }
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use crate::fs_walk::SKIPPED_DIRS;
+use crate::notifier::Notifier;
+use crate::resources::ResourceRoute;
+use crate::scan_store::{ScanRecord, ScanStore};
+use crate::semgrep_wrapper::SemgrepWrapper;
+
+/// Quiet period after the last file event before a subscribed scan is re-run.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Tracks `resources/subscribe` state and keeps subscribed scans fresh.
+///
+/// Each subscribed scan gets one background task that watches the scan's target paths
+/// and, after changes settle, re-runs the scan and sends `notifications/resources/updated`
+/// for every subscribed URI that reads from it.
+pub struct SubscriptionManager {
+    scans: Arc<ScanStore>,
+    semgrep: SemgrepWrapper,
+    notifier: Notifier,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Subscribed URI -> ID of the scan it reads from (`None` for resources without live updates).
+    subscriptions: HashMap<String, Option<String>>,
+    /// Scan ID -> watch task.
+    watchers: HashMap<String, JoinHandle<()>>,
+}
+
+impl SubscriptionManager {
+    pub fn new(scans: Arc<ScanStore>, semgrep: SemgrepWrapper, notifier: Notifier) -> Self {
+        Self { scans, semgrep, notifier, inner: Arc::new(Mutex::new(Inner::default())) }
+    }
+
+    /// A handle for background tasks that only need to know who is subscribed.
+    pub fn subscribers(&self) -> Subscribers {
+        Subscribers { inner: self.inner.clone() }
+    }
+
+    pub fn subscribe(&self, uri: &str, route: &ResourceRoute) -> Result<()> {
+        let scan_id = match route {
+            ResourceRoute::Scan { scan_id } | ResourceRoute::ScanFinding { scan_id, .. } => {
+                let scan = self.scans.get(scan_id).ok_or_else(|| anyhow::anyhow!("Scan not found: {}", scan_id))?;
+                Some(scan.id.clone())
+            }
+            _ => None,
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.subscriptions.insert(uri.to_string(), scan_id.clone());
+        if let Some(id) = scan_id {
+            self.ensure_watcher(&mut inner, &id);
+        }
+        info!("Subscribed to {}", uri);
+        Ok(())
+    }
+
+    pub fn unsubscribe(&self, uri: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.subscriptions.remove(uri).is_some() {
+            info!("Unsubscribed from {}", uri);
+        }
+        prune_watchers(&mut inner);
+    }
+
+    /// Called after a new scan is stored: `semgrep://scan/latest` and the findings under it
+    /// now point at it. A finding URI is only reported as updated if the new scan has it.
+    pub fn scan_stored(&self, record: &ScanRecord) {
+        let mut inner = self.inner.lock().unwrap();
+        let latest_uris: Vec<(String, Option<String>)> = inner
+            .subscriptions
+            .keys()
+            .filter_map(|uri| match ResourceRoute::parse(uri)? {
+                ResourceRoute::Scan { scan_id } if scan_id == "latest" => Some((uri.clone(), None)),
+                ResourceRoute::ScanFinding { scan_id, fingerprint } if scan_id == "latest" => Some((uri.clone(), Some(fingerprint))),
+                _ => None,
+            })
+            .collect();
+        if latest_uris.is_empty() {
+            return;
+        }
+        for (uri, fingerprint) in &latest_uris {
+            inner.subscriptions.insert(uri.clone(), Some(record.id.clone()));
+            if fingerprint.as_ref().is_none_or(|fp| record.finding(fp).is_some()) {
+                self.notifier.notify("notifications/resources/updated", Some(json!({ "uri": uri })));
+            }
+        }
+        self.ensure_watcher(&mut inner, &record.id);
+        prune_watchers(&mut inner);
+    }
+
+    fn ensure_watcher(&self, inner: &mut Inner, scan_id: &str) {
+        if inner.watchers.contains_key(scan_id) {
+            return;
+        }
+        let task = tokio::spawn(watch_scan(scan_id.to_string(), self.scans.clone(), self.semgrep.clone(), self.notifier.clone(), self.inner.clone()));
+        inner.watchers.insert(scan_id.to_string(), task);
+    }
+}
+
+impl Drop for SubscriptionManager {
+    fn drop(&mut self) {
+        for (_, task) in self.inner.lock().unwrap().watchers.drain() {
+            task.abort();
+        }
+    }
+}
+
+/// Read-only view of the subscribed URIs.
+#[derive(Clone)]
+pub struct Subscribers {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Subscribers {
+    pub fn contains(&self, uri: &str) -> bool {
+        self.inner.lock().unwrap().subscriptions.contains_key(uri)
+    }
+}
+
+fn prune_watchers(inner: &mut Inner) {
+    let Inner { subscriptions, watchers } = inner;
+    watchers.retain(|scan_id, task| {
+        let used = subscriptions.values().any(|id| id.as_deref() == Some(scan_id.as_str()));
+        if !used {
+            task.abort();
+        }
+        used
+    });
+}
+
+/// A scan target resolved to what is actually watched on disk.
+#[derive(Clone)]
+struct WatchTarget {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+impl WatchTarget {
+    fn covers(&self, changed: &Path) -> bool {
+        if self.is_dir {
+            changed.starts_with(&self.path)
+                && !changed
+                    .strip_prefix(&self.path)
+                    .map(|rel| rel.components().any(|c| SKIPPED_DIRS.iter().any(|skip| c.as_os_str() == *skip)))
+                    .unwrap_or(false)
+        } else {
+            changed == self.path
+        }
+    }
+}
+
+async fn watch_scan(scan_id: String, scans: Arc<ScanStore>, semgrep: SemgrepWrapper, notifier: Notifier, inner: Arc<Mutex<Inner>>) {
+    let Some(scan) = scans.get(&scan_id) else { return };
+
+    let targets: Vec<WatchTarget> = scan
+        .paths
+        .iter()
+        .filter_map(|p| std::fs::canonicalize(p).ok())
+        .map(|path| WatchTarget { is_dir: path.is_dir(), path })
+        .collect();
+    if targets.is_empty() {
+        warn!("Scan {} has no watchable paths", scan_id);
+        return;
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    let filter_targets = targets.clone();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else { return };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+            return;
+        }
+        if event.paths.iter().any(|p| filter_targets.iter().any(|t| t.covers(p))) {
+            let _ = tx.send(());
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(w) => w,
+        Err(e) => {
+            warn!("Failed to start file watcher for scan {}: {}", scan_id, e);
+            return;
+        }
+    };
+    for target in &targets {
+        // Files are watched through their parent so editors that save by rename are still seen.
+        let (path, mode) = if target.is_dir {
+            (target.path.as_path(), RecursiveMode::Recursive)
+        } else {
+            (target.path.parent().unwrap_or(&target.path), RecursiveMode::NonRecursive)
+        };
+        if let Err(e) = watcher.watch(path, mode) {
+            warn!("Failed to watch {}: {}", path.display(), e);
+        }
+    }
+
+    while rx.recv().await.is_some() {
+        while let Ok(Some(())) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {}
+
+        let Some(previous) = scans.get(&scan_id) else { break };
+        info!("Files changed, re-running scan {}", scan_id);
+        let output = match previous.source.run(&semgrep, previous.paths.clone()).await {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("Re-scan of scan {} failed: {:#}", scan_id, e);
+                continue;
+            }
+        };
+        let Some(updated) = scans.update(&scan_id, output) else { break };
+        if updated.results() == previous.results() {
+            continue;
+        }
+
+        // A finding URI only changed if that finding did.
+        let uris: Vec<String> = inner
+            .lock()
+            .unwrap()
+            .subscriptions
+            .iter()
+            .filter(|(_, id)| id.as_deref() == Some(scan_id.as_str()))
+            .filter(|(uri, _)| match ResourceRoute::parse(uri) {
+                Some(ResourceRoute::ScanFinding { fingerprint, .. }) => previous.finding(&fingerprint) != updated.finding(&fingerprint),
+                _ => true,
+            })
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        for uri in uris {
+            notifier.notify("notifications/resources/updated", Some(json!({ "uri": uri })));
+        }
+    }
+
+    drop(watcher);
+}
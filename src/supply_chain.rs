@@ -0,0 +1,747 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use crate::fs_walk::walk;
+
+/// A dependency pinned in a lockfile, normalized across ecosystems.
+///
+/// Two entries are the same dependency when ecosystem, name, version, and lockfile match;
+/// `line` only says where it was first seen.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Dependency {
+    /// OSV ecosystem name (`crates.io`, `npm`, `PyPI`, `Go`, `RubyGems`, `Packagist`).
+    pub ecosystem: String,
+    pub name: String,
+    pub version: String,
+    pub lockfile: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+impl Dependency {
+    fn key(&self) -> (&str, &str, &str, &str) {
+        (&self.ecosystem, &self.name, &self.version, &self.lockfile)
+    }
+}
+
+impl PartialEq for Dependency {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Dependency {}
+
+impl PartialOrd for Dependency {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Dependency {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplyChainReport {
+    pub lockfiles: Vec<String>,
+    pub dependency_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<Dependency>>,
+    pub results: Vec<Value>,
+    pub errors: Vec<String>,
+    /// Caveats about the results, such as versions that could only be approximated.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+pub struct SupplyChainScanner;
+
+impl SupplyChainScanner {
+    /// Finds lockfiles under `roots`, parses them, and matches every dependency against the
+    /// OSV advisories stored (as `.json` files) under `advisory_db`.
+    pub fn scan(roots: &[PathBuf], advisory_db: &Path, include_dependencies: bool) -> Result<SupplyChainReport> {
+        let db = AdvisoryDb::load(advisory_db)?;
+
+        let mut lockfiles = Vec::new();
+        for root in roots {
+            find_lockfiles(root, &mut lockfiles);
+        }
+        lockfiles.sort();
+        lockfiles.dedup();
+
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut dependencies = BTreeSet::new();
+        for lockfile in &lockfiles {
+            match parse_lockfile(lockfile) {
+                Ok(deps) => dependencies.extend(deps),
+                Err(e) => errors.push(format!("{}: {:#}", lockfile.display(), e)),
+            }
+            if matches!(lockfile_kind(lockfile), Some(LockfileKind::GoSum)) && !lockfile.with_file_name("go.mod").is_file() {
+                warnings.push(format!(
+                    "{}: no go.mod next to it, so each module is assumed to be at the highest version go.sum lists, which may not be the one the build selects",
+                    lockfile.display()
+                ));
+            }
+        }
+
+        let mut usage = UsageIndex::new(roots);
+        let mut results = Vec::new();
+        for dep in &dependencies {
+            for advisory in db.matching(dep) {
+                let evidence = usage.find_import(dep);
+                results.push(advisory.to_finding(dep, evidence));
+            }
+        }
+
+        Ok(SupplyChainReport {
+            lockfiles: lockfiles.iter().map(|p| p.display().to_string()).collect(),
+            dependency_count: dependencies.len(),
+            dependencies: include_dependencies.then(|| dependencies.into_iter().collect()),
+            results,
+            errors,
+            warnings,
+        })
+    }
+}
+
+fn find_lockfiles(root: &Path, out: &mut Vec<PathBuf>) {
+    walk(root, &mut |path| {
+        if lockfile_kind(path).is_some() {
+            out.push(path.to_path_buf());
+        }
+    });
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LockfileKind {
+    Cargo,
+    NpmPackageLock,
+    Yarn,
+    Poetry,
+    Pipfile,
+    Requirements,
+    GoSum,
+    Gemfile,
+    Composer,
+}
+
+fn lockfile_kind(path: &Path) -> Option<LockfileKind> {
+    let name = path.file_name()?.to_str()?;
+    Some(match name {
+        "Cargo.lock" => LockfileKind::Cargo,
+        "package-lock.json" | "npm-shrinkwrap.json" => LockfileKind::NpmPackageLock,
+        "yarn.lock" => LockfileKind::Yarn,
+        "poetry.lock" => LockfileKind::Poetry,
+        "Pipfile.lock" => LockfileKind::Pipfile,
+        "go.sum" => LockfileKind::GoSum,
+        "Gemfile.lock" => LockfileKind::Gemfile,
+        "composer.lock" => LockfileKind::Composer,
+        _ if name.starts_with("requirements") && name.ends_with(".txt") => LockfileKind::Requirements,
+        _ => return None,
+    })
+}
+
+fn parse_lockfile(path: &Path) -> Result<Vec<Dependency>> {
+    let kind = lockfile_kind(path).context("Unsupported lockfile")?;
+    let content = std::fs::read_to_string(path).context("Failed to read lockfile")?;
+    let lockfile = path.display().to_string();
+    let dep = |ecosystem: &str, name: &str, version: &str, line: Option<usize>| Dependency {
+        ecosystem: ecosystem.to_string(),
+        name: name.to_string(),
+        version: version.to_string(),
+        lockfile: lockfile.clone(),
+        line,
+    };
+
+    let deps = match kind {
+        LockfileKind::Cargo => parse_toml_packages(&content)
+            .into_iter()
+            .map(|(name, version, line)| dep("crates.io", &name, &version, Some(line)))
+            .collect(),
+        LockfileKind::Poetry => parse_toml_packages(&content)
+            .into_iter()
+            .map(|(name, version, line)| dep("PyPI", &name, &version, Some(line)))
+            .collect(),
+        LockfileKind::NpmPackageLock => {
+            let json: Value = serde_json::from_str(&content).context("Invalid package-lock.json")?;
+            let mut deps = Vec::new();
+            if let Some(packages) = json.get("packages").and_then(|p| p.as_object()) {
+                // lockfileVersion 2/3: keys are install paths such as "node_modules/a/node_modules/b"
+                for (key, info) in packages {
+                    let Some((_, name)) = key.rsplit_once("node_modules/") else { continue };
+                    if let Some(version) = info.get("version").and_then(|v| v.as_str()) {
+                        deps.push(dep("npm", name, version, None));
+                    }
+                }
+            } else if let Some(dependencies) = json.get("dependencies") {
+                collect_npm_v1(dependencies, &mut |name, version| deps.push(dep("npm", name, version, None)));
+            }
+            deps
+        }
+        LockfileKind::Yarn => parse_yarn_lock(&content)
+            .into_iter()
+            .map(|(name, version, line)| dep("npm", &name, &version, Some(line)))
+            .collect(),
+        LockfileKind::Pipfile => {
+            let json: Value = serde_json::from_str(&content).context("Invalid Pipfile.lock")?;
+            let mut deps = Vec::new();
+            for section in ["default", "develop"] {
+                let Some(packages) = json.get(section).and_then(|p| p.as_object()) else { continue };
+                for (name, info) in packages {
+                    if let Some(version) = info.get("version").and_then(|v| v.as_str()) {
+                        deps.push(dep("PyPI", name, version.trim_start_matches("=="), None));
+                    }
+                }
+            }
+            deps
+        }
+        LockfileKind::Requirements => content
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let line = line.split('#').next()?.trim();
+                let (name, version) = line.split_once("==")?;
+                let name = name.split('[').next()?.trim();
+                let version = version.split([';', ' ']).next()?.trim();
+                (!name.is_empty() && !version.is_empty()).then(|| dep("PyPI", name, version, Some(i + 1)))
+            })
+            .collect(),
+        LockfileKind::GoSum => {
+            let go_mod = std::fs::read_to_string(path.with_file_name("go.mod")).ok();
+            parse_go_sum(&content, go_mod.as_deref())
+                .into_iter()
+                .map(|(module, version, line)| dep("Go", &module, &version, Some(line)))
+                .collect()
+        }
+        LockfileKind::Gemfile => content
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                // Resolved gems are indented by exactly four spaces: "    name (1.2.3)"
+                let spec = line.strip_prefix("    ")?;
+                if spec.starts_with(' ') {
+                    return None;
+                }
+                let (name, rest) = spec.split_once(" (")?;
+                let version = rest.strip_suffix(')')?.split('-').next()?;
+                Some(dep("RubyGems", name, version, Some(i + 1)))
+            })
+            .collect(),
+        LockfileKind::Composer => {
+            let json: Value = serde_json::from_str(&content).context("Invalid composer.lock")?;
+            let mut deps = Vec::new();
+            for section in ["packages", "packages-dev"] {
+                let Some(packages) = json.get(section).and_then(|p| p.as_array()) else { continue };
+                for package in packages {
+                    let name = package.get("name").and_then(|v| v.as_str());
+                    let version = package.get("version").and_then(|v| v.as_str());
+                    if let (Some(name), Some(version)) = (name, version) {
+                        deps.push(dep("Packagist", name, version.trim_start_matches('v'), None));
+                    }
+                }
+            }
+            deps
+        }
+    };
+    Ok(deps)
+}
+
+/// Extracts `name`/`version` pairs from the `[[package]]` tables of Cargo.lock and poetry.lock.
+fn parse_toml_packages(content: &str) -> Vec<(String, String, usize)> {
+    let mut packages = Vec::new();
+    let mut current: Option<(Option<String>, Option<String>, usize)> = None;
+
+    let mut flush = |current: &mut Option<(Option<String>, Option<String>, usize)>| {
+        if let Some((Some(name), Some(version), line)) = current.take() {
+            packages.push((name, version, line));
+        }
+    };
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            flush(&mut current);
+            if line == "[[package]]" {
+                current = Some((None, None, i + 1));
+            }
+            continue;
+        }
+        let Some((name, version, _)) = current.as_mut() else { continue };
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim() {
+                "name" => *name = Some(value),
+                "version" => *version = Some(value),
+                _ => {}
+            }
+        }
+    }
+    flush(&mut current);
+    packages
+}
+
+/// Picks the version of each module in go.sum that the build uses: the one `go.mod` requires,
+/// or else the highest one listed. go.sum also holds hashes of versions that were only
+/// considered during version selection; `/go.mod`-only lines never name a module whose code
+/// is built, so they are skipped.
+fn parse_go_sum(content: &str, go_mod: Option<&str>) -> Vec<(String, String, usize)> {
+    let required = go_mod.map(parse_go_mod_requires).unwrap_or_default();
+    let mut selected: HashMap<&str, (&str, usize)> = HashMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let mut fields = line.split_whitespace();
+        let (Some(module), Some(version)) = (fields.next(), fields.next()) else { continue };
+        if version.ends_with("/go.mod") {
+            continue;
+        }
+        let version = version.trim_start_matches('v').trim_end_matches("+incompatible");
+        let newer = match (required.get(module), selected.get(module)) {
+            (Some(wanted), _) => wanted == version,
+            (None, Some((current, _))) => compare_versions(version, current) == Ordering::Greater,
+            (None, None) => true,
+        };
+        if newer {
+            selected.insert(module, (version, i + 1));
+        }
+    }
+    let mut packages: Vec<(String, String, usize)> =
+        selected.into_iter().map(|(module, (version, line))| (module.to_string(), version.to_string(), line)).collect();
+    packages.sort_by_key(|(_, _, line)| *line);
+    packages
+}
+
+/// Module versions from the `require` directives of a go.mod, in go.sum's spelling.
+fn parse_go_mod_requires(content: &str) -> HashMap<String, String> {
+    let mut required = HashMap::new();
+    let mut in_block = false;
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        let spec = if in_block {
+            if line == ")" {
+                in_block = false;
+                continue;
+            }
+            line
+        } else if let Some(rest) = line.strip_prefix("require") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+        let mut fields = spec.split_whitespace();
+        if let (Some(module), Some(version)) = (fields.next(), fields.next()) {
+            required.insert(module.to_string(), version.trim_start_matches('v').trim_end_matches("+incompatible").to_string());
+        }
+    }
+    required
+}
+
+fn parse_yarn_lock(content: &str) -> Vec<(String, String, usize)> {
+    let mut packages = Vec::new();
+    let mut current_name: Option<(String, usize)> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            // Entry header such as `"@scope/pkg@^1.0.0", "@scope/pkg@^1.1.0":`
+            let first = line.trim_end_matches(':').split(',').next().unwrap_or("").trim().trim_matches('"');
+            let name = match first.strip_prefix('@') {
+                Some(rest) => rest.split_once('@').map(|(n, _)| format!("@{}", n)),
+                None => first.split_once('@').map(|(n, _)| n.to_string()),
+            };
+            current_name = name.map(|n| (n, i + 1));
+        } else if let Some(version) = line.trim().strip_prefix("version") {
+            if let Some((name, line_no)) = current_name.take() {
+                let version = version.trim().trim_start_matches(':').trim().trim_matches('"');
+                packages.push((name, version.to_string(), line_no));
+            }
+        }
+    }
+    packages
+}
+
+fn collect_npm_v1(dependencies: &Value, push: &mut dyn FnMut(&str, &str)) {
+    let Some(deps) = dependencies.as_object() else { return };
+    for (name, info) in deps {
+        if let Some(version) = info.get("version").and_then(|v| v.as_str()) {
+            push(name, version);
+        }
+        if let Some(nested) = info.get("dependencies") {
+            collect_npm_v1(nested, push);
+        }
+    }
+}
+
+// --- Advisories ---
+
+#[derive(Debug, Deserialize, Clone)]
+struct OsvAdvisory {
+    id: String,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    details: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+    #[serde(default)]
+    database_specific: Option<Value>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct OsvAffected {
+    package: OsvPackage,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<Value>,
+}
+
+struct AdvisoryDb {
+    by_package: HashMap<(String, String), Vec<OsvAdvisory>>,
+}
+
+impl AdvisoryDb {
+    fn load(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            anyhow::bail!("Advisory database directory not found: {}", dir.display());
+        }
+
+        let mut by_package: HashMap<(String, String), Vec<OsvAdvisory>> = HashMap::new();
+        let mut files = Vec::new();
+        walk(dir, &mut |path| {
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path.to_path_buf());
+            }
+        });
+
+        for file in files {
+            let content = std::fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+            let advisories: Vec<OsvAdvisory> = match serde_json::from_str::<Value>(&content) {
+                Ok(Value::Array(items)) => items.into_iter().filter_map(|v| serde_json::from_value(v).ok()).collect(),
+                Ok(v) => serde_json::from_value(v).into_iter().collect(),
+                Err(e) => {
+                    tracing::warn!("Skipping invalid advisory file {}: {}", file.display(), e);
+                    continue;
+                }
+            };
+            for advisory in advisories {
+                for affected in &advisory.affected {
+                    let key = package_key(&affected.package.ecosystem, &affected.package.name);
+                    let entry = by_package.entry(key).or_default();
+                    if !entry.iter().any(|a| a.id == advisory.id) {
+                        entry.push(advisory.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(Self { by_package })
+    }
+
+    fn matching<'a>(&'a self, dep: &Dependency) -> impl Iterator<Item = &'a OsvAdvisory> + 'a {
+        let key = package_key(&dep.ecosystem, &dep.name);
+        let dep = dep.clone();
+        self.by_package
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter(move |advisory| advisory.affects(&dep))
+    }
+}
+
+fn package_key(ecosystem: &str, name: &str) -> (String, String) {
+    let name = match ecosystem {
+        // PEP 503 normalization
+        "PyPI" => name.to_lowercase().replace(['_', '.'], "-"),
+        "npm" | "Packagist" => name.to_lowercase(),
+        _ => name.to_string(),
+    };
+    (ecosystem.to_string(), name)
+}
+
+impl OsvAdvisory {
+    fn affects(&self, dep: &Dependency) -> bool {
+        let key = package_key(&dep.ecosystem, &dep.name);
+        self.affected
+            .iter()
+            .filter(|a| package_key(&a.package.ecosystem, &a.package.name) == key)
+            .any(|a| {
+                a.versions.iter().any(|v| compare_versions(v, &dep.version) == Ordering::Equal)
+                    || a.ranges.iter().any(|r| r.kind != "GIT" && range_affects(&r.events, &dep.version))
+            })
+    }
+
+    fn severity(&self) -> String {
+        self.database_specific
+            .as_ref()
+            .and_then(|d| d.get("severity"))
+            .and_then(|s| s.as_str())
+            .map(|s| s.to_uppercase())
+            .unwrap_or_else(|| "UNKNOWN".to_string())
+    }
+
+    fn vulnerable_range(&self, dep: &Dependency) -> String {
+        let key = package_key(&dep.ecosystem, &dep.name);
+        let mut parts = Vec::new();
+        for affected in self.affected.iter().filter(|a| package_key(&a.package.ecosystem, &a.package.name) == key) {
+            for range in affected.ranges.iter().filter(|r| r.kind != "GIT") {
+                let mut clause = Vec::new();
+                for event in &range.events {
+                    if let Some(v) = event.get("introduced").and_then(|v| v.as_str()) {
+                        if v != "0" {
+                            clause.push(format!(">= {}", v));
+                        }
+                    } else if let Some(v) = event.get("fixed").and_then(|v| v.as_str()) {
+                        clause.push(format!("< {}", v));
+                    } else if let Some(v) = event.get("last_affected").and_then(|v| v.as_str()) {
+                        clause.push(format!("<= {}", v));
+                    }
+                }
+                if !clause.is_empty() {
+                    parts.push(clause.join(", "));
+                }
+            }
+        }
+        parts.join(" || ")
+    }
+
+    /// Renders a match in the shape of a Semgrep Supply Chain result.
+    fn to_finding(&self, dep: &Dependency, import: Option<String>) -> Value {
+        let severity = match self.severity().as_str() {
+            "CRITICAL" | "HIGH" => "ERROR",
+            "MODERATE" | "MEDIUM" => "WARNING",
+            _ => "INFO",
+        };
+        let title = self.summary.clone().unwrap_or_else(|| self.id.clone());
+        let reachability = if import.is_some() { "reachable" } else { "undetermined" };
+
+        json!({
+            "check_id": format!("ssc-{}", self.id),
+            "path": dep.lockfile,
+            "start": { "line": dep.line.unwrap_or(1) },
+            "extra": {
+                "message": format!("{}@{} is affected by {}: {}", dep.name, dep.version, self.id, title),
+                "severity": severity,
+                "metadata": {
+                    "advisory_id": self.id,
+                    "aliases": self.aliases,
+                    "advisory_severity": self.severity(),
+                    "details": self.details,
+                },
+                "sca_info": {
+                    "reachable": import.is_some(),
+                    "reachability": reachability,
+                    "reachability_evidence": import,
+                    "dependency_match": {
+                        "dependency_pattern": {
+                            "ecosystem": dep.ecosystem,
+                            "package": dep.name,
+                            "semver_range": self.vulnerable_range(dep),
+                        },
+                        "found_dependency": {
+                            "ecosystem": dep.ecosystem,
+                            "package": dep.name,
+                            "version": dep.version,
+                            "line_number": dep.line,
+                        },
+                        "lockfile": dep.lockfile,
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Evaluates OSV `SEMVER`/`ECOSYSTEM` range events against `version`.
+fn range_affects(events: &[Value], version: &str) -> bool {
+    let mut parsed: Vec<(&str, &str)> = events
+        .iter()
+        .filter_map(|e| {
+            let obj = e.as_object()?;
+            let (kind, v) = obj.iter().next()?;
+            Some((kind.as_str(), v.as_str()?))
+        })
+        .collect();
+    // At equal versions `introduced` goes first, so a range introduced and fixed at the same
+    // version is empty whatever order the advisory lists them in.
+    parsed.sort_by(|a, b| compare_versions(a.1, b.1).then_with(|| (a.0 != "introduced").cmp(&(b.0 != "introduced"))));
+
+    let mut affected = false;
+    for (kind, event_version) in parsed {
+        let ord = compare_versions(version, event_version);
+        match kind {
+            "introduced" if ord != Ordering::Less => affected = true,
+            "fixed" if ord != Ordering::Less => affected = false,
+            "last_affected" if ord == Ordering::Greater => affected = false,
+            _ => {}
+        }
+    }
+    affected
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum VersionToken<'a> {
+    Num(u64),
+    Alpha(&'a str),
+}
+
+fn tokenize_version(version: &str) -> Vec<VersionToken<'_>> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let version = version.split('+').next().unwrap_or(version);
+
+    let mut tokens = Vec::new();
+    let bytes = version.as_bytes();
+    let mut start = 0;
+    while start < bytes.len() {
+        let is_digit = bytes[start].is_ascii_digit();
+        let is_alpha = bytes[start].is_ascii_alphabetic();
+        if !is_digit && !is_alpha {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < bytes.len() && (if is_digit { bytes[end].is_ascii_digit() } else { bytes[end].is_ascii_alphabetic() }) {
+            end += 1;
+        }
+        let text = &version[start..end];
+        tokens.push(if is_digit { VersionToken::Num(text.parse().unwrap_or(u64::MAX)) } else { VersionToken::Alpha(text) });
+        start = end;
+    }
+    tokens
+}
+
+/// Ecosystem-agnostic version ordering: numeric runs compare numerically, pre-release
+/// labels sort before the release they precede, and post-release labels sort after it.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn is_post(label: &str) -> bool {
+        matches!(label.to_ascii_lowercase().as_str(), "post" | "rev" | "r" | "p" | "pl")
+    }
+    // Ordering of a version that continues with `token` relative to one that ended here.
+    fn tail_order(token: &VersionToken) -> Ordering {
+        match token {
+            VersionToken::Num(0) => Ordering::Equal,
+            VersionToken::Num(_) => Ordering::Greater,
+            VersionToken::Alpha(label) if is_post(label) => Ordering::Greater,
+            VersionToken::Alpha(_) => Ordering::Less,
+        }
+    }
+
+    let a = tokenize_version(a);
+    let b = tokenize_version(b);
+    for i in 0..a.len().max(b.len()) {
+        let ord = match (a.get(i), b.get(i)) {
+            (Some(VersionToken::Num(x)), Some(VersionToken::Num(y))) => x.cmp(y),
+            (Some(VersionToken::Alpha(x)), Some(VersionToken::Alpha(y))) => x.to_ascii_lowercase().cmp(&y.to_ascii_lowercase()),
+            (Some(VersionToken::Num(_)), Some(VersionToken::Alpha(y))) => if is_post(y) { Ordering::Less } else { Ordering::Greater },
+            (Some(VersionToken::Alpha(x)), Some(VersionToken::Num(_))) => if is_post(x) { Ordering::Greater } else { Ordering::Less },
+            (Some(x), None) => tail_order(x),
+            (None, Some(y)) => tail_order(y).reverse(),
+            (None, None) => Ordering::Equal,
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+// --- Reachability ---
+
+/// Lazily built index of first-party source files, used to tell whether a vulnerable
+/// package is imported directly by the scanned code.
+struct UsageIndex {
+    roots: Vec<PathBuf>,
+    files: Option<Vec<(PathBuf, String)>>,
+}
+
+impl UsageIndex {
+    fn new(roots: &[PathBuf]) -> Self {
+        Self { roots: roots.to_vec(), files: None }
+    }
+
+    fn files(&mut self) -> &[(PathBuf, String)] {
+        let roots = &self.roots;
+        self.files.get_or_insert_with(|| {
+            const SOURCE_EXTENSIONS: &[&str] = &["rs", "js", "jsx", "mjs", "cjs", "ts", "tsx", "py", "go", "rb", "php"];
+            const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+            let mut files = Vec::new();
+            let mut visit = |path: &Path| {
+                let is_source = path.extension().and_then(|e| e.to_str()).is_some_and(|e| SOURCE_EXTENSIONS.contains(&e));
+                let small = std::fs::metadata(path).is_ok_and(|m| m.len() <= MAX_FILE_SIZE);
+                if is_source && small {
+                    if let Ok(content) = std::fs::read_to_string(path) {
+                        files.push((path.to_path_buf(), content));
+                    }
+                }
+            };
+            for root in roots {
+                walk(root, &mut visit);
+            }
+            files
+        })
+    }
+
+    /// Returns `path:line` of the first direct import of `dep`, if any.
+    fn find_import(&mut self, dep: &Dependency) -> Option<String> {
+        let (extensions, needles): (&[&str], Vec<String>) = match dep.ecosystem.as_str() {
+            "crates.io" => {
+                let ident = dep.name.replace('-', "_");
+                (&["rs"], vec![format!("use {}", ident), format!("{}::", ident), format!("extern crate {}", ident)])
+            }
+            "npm" => (
+                &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
+                ["'", "\""]
+                    .iter()
+                    .flat_map(|q| [format!("require({q}{}{q}", dep.name), format!("from {q}{}{q}", dep.name), format!("import({q}{}{q}", dep.name), format!("require({q}{}/", dep.name), format!("from {q}{}/", dep.name)])
+                    .collect(),
+            ),
+            "PyPI" => {
+                let module = dep.name.to_lowercase().replace('-', "_");
+                (&["py"], vec![format!("import {}", module), format!("from {} ", module), format!("from {}.", module)])
+            }
+            "Go" => (&["go"], vec![format!("\"{}\"", dep.name), format!("\"{}/", dep.name)]),
+            "RubyGems" => (&["rb"], vec![format!("require '{}'", dep.name), format!("require \"{}\"", dep.name)]),
+            _ => return None,
+        };
+
+        self.files().iter().find_map(|(path, content)| {
+            let ext = path.extension().and_then(|e| e.to_str())?;
+            if !extensions.contains(&ext) {
+                return None;
+            }
+            content
+                .lines()
+                .position(|line| needles.iter().any(|n| line.contains(n.as_str())))
+                .map(|i| format!("{}:{}", path.display(), i + 1))
+        })
+    }
+}
@@ -0,0 +1,239 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use crate::api_client::{ApiClient, FindingSummary, FindingsQuery, IssueType, TriageReason, TriageRequest, TriageState};
+use crate::config::Config;
+use crate::handler::{app_token, get_project_summary, internal_error, list_project_summaries};
+use crate::protocol::{CallToolResult, JsonRpcError, ToolAnnotations};
+use super::{invalid_params, json_result, read_only, required_str, text_result, ToolContext, ToolHandler};
+
+pub struct Findings;
+
+#[async_trait]
+impl ToolHandler for Findings {
+    fn name(&self) -> &str {
+        "semgrep_findings"
+    }
+
+    fn description(&self) -> &str {
+        "Fetch Semgrep findings"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "issue_type": { "type": "string", "enum": ["sast", "sca"], "description": "Type of findings to fetch" },
+                "status": { "type": "string", "enum": ["open", "reviewing", "fixing", "ignored", "fixed"], "description": "Finding status" },
+                "repos": { "type": "array", "items": { "type": "string" }, "description": "Repository names to include" },
+                "severities": { "type": "array", "items": { "type": "string", "enum": ["low", "medium", "high", "critical"] } },
+                "rules": { "type": "array", "items": { "type": "string" }, "description": "Rule IDs to include" },
+                "confidence": { "type": "string", "enum": ["low", "medium", "high"] },
+                "autotriage_verdict": { "type": "string", "enum": ["true_positive", "false_positive"], "description": "Semgrep Assistant triage verdict" },
+                "since": { "type": "string", "description": "Only findings first seen at or after this time (RFC 3339 or epoch seconds)" },
+                "ref": { "type": "string", "description": "Git ref (branch) the findings were reported on" },
+                "dedup": { "type": "boolean", "description": "Collapse findings that appear on several refs" },
+                "page": { "type": "integer", "minimum": 0 },
+                "page_size": { "type": "integer", "minimum": 1, "maximum": 3000 },
+                "output": { "type": "string", "enum": ["summary", "full"], "default": "summary", "description": "Compact summary or the full API payload" }
+            },
+            "required": []
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Semgrep App Findings", true)
+    }
+
+    fn unavailable_reason(&self, _config: &Config) -> Option<String> {
+        missing_token()
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, mut args: Value) -> Result<CallToolResult, JsonRpcError> {
+        ctx.require_online("semgrep_findings")?;
+        let token = app_token()?;
+        let full = match args.as_object_mut().and_then(|obj| obj.remove("output")) {
+            Some(Value::String(s)) if s == "full" => true,
+            Some(Value::String(s)) if s == "summary" => false,
+            None | Some(Value::Null) => false,
+            Some(_) => return Err(invalid_params("output must be 'summary' or 'full'")),
+        };
+        let query: FindingsQuery = serde_json::from_value(args).map_err(|e| invalid_params(format!("Invalid params: {}", e)))?;
+        let res = ApiClient::get_findings(&token, &query).await.map_err(internal_error)?;
+        if full {
+            return Ok(json_result(&res));
+        }
+        let findings: Vec<FindingSummary> = res.findings.iter().map(FindingSummary::from).collect();
+        Ok(text_result(serde_json::to_string(&json!({ "count": findings.len(), "findings": findings })).unwrap()))
+    }
+}
+
+pub struct TriageFindings;
+
+#[async_trait]
+impl ToolHandler for TriageFindings {
+    fn name(&self) -> &str {
+        "triage_findings"
+    }
+
+    fn description(&self) -> &str {
+        "Change the triage state of Semgrep App findings and attach a reason or note"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "issue_type": { "type": "string", "enum": ["sast", "sca"], "description": "Type of the findings being triaged" },
+                "issue_ids": { "type": "array", "items": { "type": "integer" }, "description": "IDs of the findings to triage" },
+                "status": { "type": "string", "enum": ["ignored", "reviewing", "fixing", "reopened"], "description": "New triage state" },
+                "reason": { "type": "string", "enum": ["false_positive", "acceptable_risk", "no_time"], "description": "Triage reason, only valid with status 'ignored'" },
+                "note": { "type": "string", "description": "Note to attach to the findings" }
+            },
+            "required": ["issue_type", "issue_ids"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        // Triage changes are reversible, so they are not destructive. Repeating a call
+        // attaches its note again, so it is not idempotent.
+        ToolAnnotations {
+            title: Some("Triage Findings".to_string()),
+            readOnlyHint: Some(false),
+            destructiveHint: Some(false),
+            idempotentHint: Some(false),
+            openWorldHint: Some(true),
+        }
+    }
+
+    fn unavailable_reason(&self, config: &Config) -> Option<String> {
+        if !config.enable_write_tools {
+            return Some("start sidero with --enable-write-tools".to_string());
+        }
+        missing_token()
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        ctx.require_online("triage_findings")?;
+        let token = app_token()?;
+        let request = parse_triage_request(&args)?;
+        let res = ApiClient::triage_findings(&token, &request).await.map_err(internal_error)?;
+        Ok(json_result(&res))
+    }
+}
+
+pub struct ListProjects;
+
+#[async_trait]
+impl ToolHandler for ListProjects {
+    fn name(&self) -> &str {
+        "list_projects"
+    }
+
+    fn description(&self) -> &str {
+        "List Semgrep App projects (the values accepted by semgrep_findings 'repos')"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "include_finding_counts": { "type": "boolean", "default": false, "description": "Also count open findings per project" }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("List Projects", true)
+    }
+
+    fn unavailable_reason(&self, _config: &Config) -> Option<String> {
+        missing_token()
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        ctx.require_online("list_projects")?;
+        let token = app_token()?;
+        let include_counts = args.get("include_finding_counts").and_then(|v| v.as_bool()).unwrap_or(false);
+        let projects = list_project_summaries(&token, include_counts).await?;
+        Ok(json_result(&projects))
+    }
+}
+
+pub struct GetProject;
+
+#[async_trait]
+impl ToolHandler for GetProject {
+    fn name(&self) -> &str {
+        "get_project"
+    }
+
+    fn description(&self) -> &str {
+        "Get a Semgrep App project with its tags, latest scan time, and open finding counts"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "description": "Project name, e.g. 'org/repo'" }
+            },
+            "required": ["name"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Get Project", true)
+    }
+
+    fn unavailable_reason(&self, _config: &Config) -> Option<String> {
+        missing_token()
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        ctx.require_online("get_project")?;
+        let token = app_token()?;
+        let name = required_str(&args, "name")?;
+        let project = get_project_summary(&token, name).await?;
+        Ok(json_result(&project))
+    }
+}
+
+/// Semgrep App tools are only offered once a token is available.
+fn missing_token() -> Option<String> {
+    ApiClient::token().is_none().then(|| "SEMGREP_APP_TOKEN is not set (set it or run `semgrep login`)".to_string())
+}
+
+fn parse_triage_request(args: &Value) -> Result<TriageRequest, JsonRpcError> {
+    let invalid = |message: &str| invalid_params(message);
+
+    let issue_type: IssueType = serde_json::from_value(args.get("issue_type").cloned().ok_or_else(|| invalid("Missing issue_type"))?)
+        .map_err(|_| invalid("issue_type must be 'sast' or 'sca'"))?;
+    let issue_ids: Vec<u64> = serde_json::from_value(args.get("issue_ids").cloned().unwrap_or(json!([]))).map_err(|_| invalid("Invalid issue_ids"))?;
+    if issue_ids.is_empty() {
+        return Err(invalid("issue_ids must not be empty"));
+    }
+    let new_triage_state: Option<TriageState> = match args.get("status") {
+        Some(v) if !v.is_null() => Some(serde_json::from_value(v.clone()).map_err(|_| invalid("Invalid status"))?),
+        _ => None,
+    };
+    let new_triage_reason: Option<TriageReason> = match args.get("reason") {
+        Some(v) if !v.is_null() => Some(serde_json::from_value(v.clone()).map_err(|_| invalid("Invalid reason"))?),
+        _ => None,
+    };
+    let new_note = args.get("note").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+    if new_triage_reason.is_some() && new_triage_state != Some(TriageState::Ignored) {
+        return Err(invalid("reason can only be set together with status 'ignored'"));
+    }
+    if new_triage_state.is_none() && new_note.is_none() {
+        return Err(invalid("Provide a status, a note, or both"));
+    }
+
+    Ok(TriageRequest {
+        issue_type,
+        issue_ids,
+        new_triage_state,
+        new_triage_reason,
+        new_note,
+    })
+}
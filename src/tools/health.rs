@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use crate::doctor::DoctorReport;
+use crate::protocol::{CallToolResult, JsonRpcError, ToolAnnotations};
+use super::{json_result, read_only, ToolContext, ToolHandler};
+
+/// The `sidero doctor` checks as a tool, so a client can diagnose a broken setup itself.
+pub struct HealthCheck;
+
+#[async_trait]
+impl ToolHandler for HealthCheck {
+    fn name(&self) -> &str {
+        "health_check"
+    }
+
+    fn description(&self) -> &str {
+        "Check the Semgrep installation and sidero's environment (semgrep version, Python, temp dir, network, token, workspace config), with a fix for each problem"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "workspace": { "type": "string", "description": "Workspace root to check for Semgrep config (defaults to the server's working directory)" }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        // Reaches out to semgrep.dev unless offline.
+        read_only("Health Check", true)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let workspace = args.get("workspace").and_then(|v| v.as_str()).map(PathBuf::from);
+        let report = DoctorReport::run(ctx.config, workspace.as_deref()).await;
+        Ok(json_result(&report))
+    }
+}
@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use crate::handler::internal_error;
+use crate::protocol::{CallToolResult, JsonRpcError, ToolAnnotations};
+use crate::rule_library::{Fixture, LOCAL_RULE_PREFIX};
+use super::{array_arg, invalid_params, json_result, read_only, required_str, text_result, ToolContext, ToolHandler};
+
+pub struct SaveRule;
+
+#[async_trait]
+impl ToolHandler for SaveRule {
+    fn name(&self) -> &str {
+        "save_rule"
+    }
+
+    fn description(&self) -> &str {
+        "Save a custom rule and its test fixtures to the local rule library; scan with it later using config 'local-rule/<id>'"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "Library ID (letters, digits, '.', '-', '_')" },
+                "rule": { "type": "string", "description": "Semgrep rule YAML" },
+                "description": { "type": "string" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "fixtures": {
+                    "type": "array",
+                    "description": "Test files for the rule, usually annotated with 'ruleid:' / 'ok:' comments",
+                    "items": {
+                        "type": "object",
+                        "properties": { "name": { "type": "string" }, "content": { "type": "string" } },
+                        "required": ["name", "content"]
+                    }
+                },
+                "overwrite": { "type": "boolean", "description": "Replace an existing rule with the same ID (default false)" }
+            },
+            "required": ["id", "rule"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        // With `overwrite`, a saved rule replaces the existing one.
+        ToolAnnotations {
+            title: Some("Save Rule".to_string()),
+            readOnlyHint: Some(false),
+            destructiveHint: Some(true),
+            idempotentHint: Some(true),
+            openWorldHint: Some(false),
+        }
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let id = required_str(&args, "id")?;
+        let rule = required_str(&args, "rule")?;
+        let description = args.get("description").and_then(|v| v.as_str()).map(|s| s.to_string());
+        let tags: Vec<String> = array_arg(&args, "tags")?;
+        let fixtures: Vec<Fixture> = array_arg(&args, "fixtures")?;
+        let overwrite = args.get("overwrite").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let metadata = ctx.config.rule_library().save(id, rule, description, tags, fixtures, overwrite).map_err(|e| invalid_params(e.to_string()))?;
+        Ok(json_result(&json!({
+            "saved": metadata,
+            "config": format!("{}{}", LOCAL_RULE_PREFIX, metadata.id),
+            "uri": format!("semgrep://local-rule/{}", metadata.id),
+        })))
+    }
+}
+
+pub struct ListRules;
+
+#[async_trait]
+impl ToolHandler for ListRules {
+    fn name(&self) -> &str {
+        "list_rules"
+    }
+
+    fn description(&self) -> &str {
+        "List rules saved in the local rule library"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "tag": { "type": "string", "description": "Only list rules with this tag" }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("List Saved Rules", false)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let tag = args.get("tag").and_then(|v| v.as_str());
+        let library = ctx.config.rule_library();
+        let rules: Vec<_> = library
+            .list()
+            .map_err(internal_error)?
+            .into_iter()
+            .filter(|rule| tag.is_none_or(|tag| rule.tags.iter().any(|t| t == tag)))
+            .collect();
+        Ok(json_result(&json!({ "library": library.root(), "count": rules.len(), "rules": rules })))
+    }
+}
+
+pub struct GetRule;
+
+#[async_trait]
+impl ToolHandler for GetRule {
+    fn name(&self) -> &str {
+        "get_rule"
+    }
+
+    fn description(&self) -> &str {
+        "Get a saved rule with its metadata and test fixtures"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "id": { "type": "string" } },
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Get Saved Rule", false)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let id = required_str(&args, "id")?;
+        let rule = ctx.config.rule_library().get(id).map_err(|e| invalid_params(e.to_string()))?;
+        Ok(json_result(&rule))
+    }
+}
+
+pub struct DeleteRule;
+
+#[async_trait]
+impl ToolHandler for DeleteRule {
+    fn name(&self) -> &str {
+        "delete_rule"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a rule from the local rule library"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "id": { "type": "string" } },
+            "required": ["id"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations {
+            title: Some("Delete Saved Rule".to_string()),
+            readOnlyHint: Some(false),
+            destructiveHint: Some(true),
+            idempotentHint: Some(true),
+            openWorldHint: Some(false),
+        }
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let id = required_str(&args, "id")?;
+        ctx.config.rule_library().delete(id).map_err(|e| invalid_params(e.to_string()))?;
+        Ok(text_result(format!("Deleted rule '{}'", id)))
+    }
+}
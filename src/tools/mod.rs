@@ -0,0 +1,259 @@
+use async_trait::async_trait;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use crate::api_client::ApiClient;
+use crate::config::Config;
+use crate::notifier::Notifier;
+use crate::protocol::{CallToolResult, Content, JsonRpcError, Tool, ToolAnnotations};
+use crate::scan_store::{ScanRecord, ScanSource, ScanStore};
+use crate::semgrep_wrapper::SemgrepWrapper;
+use crate::subscriptions::SubscriptionManager;
+use crate::watch::WatchManager;
+
+mod app;
+mod health;
+mod library;
+mod scanning;
+mod watch;
+
+/// How often the Semgrep settings file is re-read when no change to it has been seen, in
+/// case the filesystem does not report changes.
+const SETTINGS_RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long to wait after a change to the settings file before reading it.
+const SETTINGS_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A tool the server exposes through `tools/list` and `tools/call`.
+#[async_trait]
+pub trait ToolHandler: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// JSON Schema of the tool's arguments.
+    fn input_schema(&self) -> Value;
+
+    fn annotations(&self) -> ToolAnnotations;
+
+    /// Why the tool can't be used right now, if it can't. Unavailable tools are left out of
+    /// `tools/list` and refused by `tools/call`; the answer may change while the server runs.
+    fn unavailable_reason(&self, _config: &Config) -> Option<String> {
+        None
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError>;
+}
+
+/// Server state a tool call can use.
+pub struct ToolContext<'a> {
+    pub config: &'a Config,
+    pub semgrep: &'a SemgrepWrapper,
+    pub scans: &'a Arc<ScanStore>,
+    pub subscriptions: &'a SubscriptionManager,
+    pub watches: &'a WatchManager,
+}
+
+impl ToolContext<'_> {
+    pub fn require_online(&self, what: &str) -> Result<(), JsonRpcError> {
+        crate::handler::require_online(self.config, what)
+    }
+
+    /// Records a finished scan and lets subscribers know about it.
+    pub fn store_scan(&self, source: ScanSource, paths: Vec<String>, output: Value) -> Arc<ScanRecord> {
+        let record = self.scans.insert(source, paths, output);
+        self.subscriptions.scan_stored(&record);
+        record
+    }
+}
+
+/// The tools a server offers, in listing order. Drives both `tools/list` and `tools/call`.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn ToolHandler>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The tools sidero ships with.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(scanning::SemgrepScan);
+        registry.register(scanning::ScanWithCustomRule);
+        registry.register(scanning::SupplyChainScan);
+        registry.register(scanning::SecretsScan);
+        registry.register(scanning::AbstractSyntaxTree);
+        registry.register(app::Findings);
+        registry.register(app::ListProjects);
+        registry.register(app::GetProject);
+        registry.register(scanning::Version);
+        registry.register(scanning::SupportedLanguages);
+        registry.register(watch::StartWatch);
+        registry.register(watch::StopWatch);
+        registry.register(health::HealthCheck);
+        registry.register(library::SaveRule);
+        registry.register(library::ListRules);
+        registry.register(library::GetRule);
+        registry.register(library::DeleteRule);
+        registry.register(app::TriageFindings);
+        registry
+    }
+
+    /// Adds a tool, replacing any registered tool with the same name.
+    pub fn register(&mut self, tool: impl ToolHandler + 'static) {
+        let tool: Arc<dyn ToolHandler> = Arc::new(tool);
+        match self.tools.iter_mut().find(|t| t.name() == tool.name()) {
+            Some(existing) => *existing = tool,
+            None => self.tools.push(tool),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn ToolHandler>> {
+        self.tools.iter().find(|t| t.name() == name)
+    }
+
+    /// Why `tool` is not offered, from the configured allow/deny lists or the tool itself.
+    pub fn unavailable_reason(tool: &dyn ToolHandler, config: &Config) -> Option<String> {
+        let name = tool.name().to_string();
+        if !config.enable_tools.is_empty() && !config.enable_tools.contains(&name) {
+            return Some("it is not listed in --enable-tools".to_string());
+        }
+        if config.disable_tools.contains(&name) {
+            return Some("it is listed in --disable-tools".to_string());
+        }
+        if config.read_only && tool.annotations().readOnlyHint != Some(true) {
+            return Some("sidero is running with --read-only".to_string());
+        }
+        tool.unavailable_reason(config)
+    }
+
+    /// Descriptions of the tools available under `config`.
+    pub fn list(&self, config: &Config) -> Vec<Tool> {
+        self.tools
+            .iter()
+            .filter(|tool| Self::unavailable_reason(tool.as_ref(), config).is_none())
+            .map(|tool| Tool {
+                name: tool.name().to_string(),
+                description: Some(tool.description().to_string()),
+                inputSchema: tool.input_schema(),
+                annotations: Some(tool.annotations()),
+            })
+            .collect()
+    }
+}
+
+/// Sends `notifications/tools/list_changed` whenever the set of available tools changes,
+/// which happens when `semgrep login` saves a token or `semgrep logout` removes it.
+/// Stops when dropped.
+pub struct ToolListWatcher {
+    task: JoinHandle<()>,
+}
+
+impl ToolListWatcher {
+    pub fn spawn(registry: ToolRegistry, config: Config, notifier: Notifier) -> Self {
+        let names = move || registry.list(&config).into_iter().map(|tool| tool.name).collect::<Vec<_>>();
+        let task = tokio::spawn(async move {
+            // A token in the environment can't change while the server runs.
+            if std::env::var("SEMGREP_APP_TOKEN").is_ok_and(|t| !t.is_empty()) {
+                return;
+            }
+            let Some(settings) = ApiClient::settings_file() else { return };
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            let mut watch: Option<(PathBuf, RecommendedWatcher)> = None;
+            watch_settings(&settings, &mut watch, &tx);
+            let mut token = ApiClient::token();
+            let mut listed = names();
+            loop {
+                tokio::select! {
+                    _ = rx.recv() => {}
+                    _ = tokio::time::sleep(SETTINGS_RECHECK_INTERVAL) => {}
+                }
+                // Editors and `semgrep login` write in several steps; settle before reading.
+                tokio::time::sleep(SETTINGS_DEBOUNCE).await;
+                while rx.try_recv().is_ok() {}
+                // Follow a newly created `~/.semgrep` before reading, so no write is missed.
+                watch_settings(&settings, &mut watch, &tx);
+
+                let current_token = ApiClient::token();
+                if current_token == token {
+                    continue;
+                }
+                token = current_token;
+                let current = names();
+                if current != listed {
+                    tracing::info!("Available tools changed: {}", current.join(", "));
+                    notifier.notify("notifications/tools/list_changed", None);
+                    listed = current;
+                }
+            }
+        });
+        Self { task }
+    }
+}
+
+/// Keeps `watch` on the nearest existing directory above `settings`, so the file is seen
+/// even if `~/.semgrep` is created after the server starts. Events for anything but the
+/// settings file or a directory on the way to it are dropped.
+fn watch_settings(settings: &Path, watch: &mut Option<(PathBuf, RecommendedWatcher)>, tx: &tokio::sync::mpsc::UnboundedSender<()>) {
+    let Some(dir) = settings.ancestors().skip(1).find(|dir| dir.is_dir()) else { return };
+    if watch.as_ref().is_some_and(|(watched, _)| watched == dir) {
+        return;
+    }
+    let (target, tx) = (settings.to_path_buf(), tx.clone());
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if res.is_ok_and(|event| event.paths.iter().any(|path| target.starts_with(path))) {
+            let _ = tx.send(());
+        }
+    });
+    match watcher.and_then(|mut watcher| watcher.watch(dir, RecursiveMode::NonRecursive).map(|_| watcher)) {
+        Ok(watcher) => *watch = Some((dir.to_path_buf(), watcher)),
+        Err(e) => {
+            tracing::warn!("Cannot watch {} for Semgrep logins: {}", dir.display(), e);
+            *watch = None;
+        }
+    }
+}
+
+impl Drop for ToolListWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Annotations for a tool that only reads.
+fn read_only(title: &str, open_world: bool) -> ToolAnnotations {
+    ToolAnnotations {
+        title: Some(title.to_string()),
+        readOnlyHint: Some(true),
+        destructiveHint: None,
+        idempotentHint: None,
+        openWorldHint: Some(open_world),
+    }
+}
+
+fn text_result(text: String) -> CallToolResult {
+    CallToolResult { content: vec![Content::Text { text }], isError: None }
+}
+
+fn json_result<T: serde::Serialize>(value: &T) -> CallToolResult {
+    text_result(serde_json::to_string_pretty(value).unwrap())
+}
+
+fn invalid_params(message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError { code: -32602, message: message.into(), data: None }
+}
+
+fn required_str<'a>(args: &'a Value, name: &str) -> Result<&'a str, JsonRpcError> {
+    args.get(name).and_then(|v| v.as_str()).ok_or_else(|| invalid_params(format!("Missing {}", name)))
+}
+
+/// Deserializes an array argument, treating a missing one as empty.
+fn array_arg<T: serde::de::DeserializeOwned>(args: &Value, name: &str) -> Result<Vec<T>, JsonRpcError> {
+    serde_json::from_value(args.get(name).cloned().unwrap_or(json!([]))).map_err(|_| invalid_params(format!("Invalid {}", name)))
+}
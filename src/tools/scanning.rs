@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use crate::handler::internal_error;
+use crate::protocol::{CallToolResult, JsonRpcError, ToolAnnotations};
+use crate::rule_packs::resolve_config;
+use crate::scan_store::{ScanRecord, ScanSource};
+use crate::secrets::{SecretsOptions, SecretsScanner};
+use crate::semgrep_wrapper::SemgrepWrapper;
+use crate::supply_chain::SupplyChainScanner;
+use super::{array_arg, invalid_params, json_result, read_only, required_str, text_result, ToolContext, ToolHandler};
+
+pub struct SemgrepScan;
+
+#[async_trait]
+impl ToolHandler for SemgrepScan {
+    fn name(&self) -> &str {
+        "semgrep_scan"
+    }
+
+    fn description(&self) -> &str {
+        "Run a Semgrep scan on specific paths"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "List of file paths to scan" },
+                "config": { "type": "string", "description": "Rule configuration: a registry pack (p/ci), a rule file or directory, or local/<pack> from the local rules directory" }
+            },
+            "required": ["paths"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        // Registry configs are downloaded from semgrep.dev.
+        read_only("Semgrep Scan", true)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let paths: Vec<String> = array_arg(&args, "paths")?;
+        let config = resolve_config(args.get("config").and_then(|v| v.as_str()), ctx.config).map_err(|e| invalid_params(e.to_string()))?;
+        let result = ctx.semgrep.scan(config.clone(), paths.clone()).await.map_err(internal_error)?;
+        let record = ctx.store_scan(ScanSource::Config { config }, paths, result);
+        Ok(json_result(&scan_tool_output(&record)))
+    }
+}
+
+pub struct ScanWithCustomRule;
+
+#[async_trait]
+impl ToolHandler for ScanWithCustomRule {
+    fn name(&self) -> &str {
+        "semgrep_scan_with_custom_rule"
+    }
+
+    fn description(&self) -> &str {
+        "Run a scan with a custom ad-hoc rule"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "rule": { "type": "string", "description": "YAML rule content" },
+                "code_files": { "type": "array", "items": { "type": "string" }, "description": "Files to scan" }
+            },
+            "required": ["rule", "code_files"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Scan With Custom Rule", false)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let rule = required_str(&args, "rule")?.to_string();
+        let files: Vec<String> = array_arg(&args, "code_files")?;
+        let result = ctx.semgrep.scan_with_custom_rule(rule.clone(), files.clone()).await.map_err(internal_error)?;
+        let record = ctx.store_scan(ScanSource::CustomRule { rule }, files, result);
+        Ok(json_result(&scan_tool_output(&record)))
+    }
+}
+
+pub struct SupplyChainScan;
+
+#[async_trait]
+impl ToolHandler for SupplyChainScan {
+    fn name(&self) -> &str {
+        "semgrep_supply_chain_scan"
+    }
+
+    fn description(&self) -> &str {
+        "Scan lockfiles under the given paths for dependencies with known vulnerabilities, using a local OSV advisory database"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "Files or directories to search for lockfiles" },
+                "advisory_db": { "type": "string", "description": "Directory of OSV JSON advisories (defaults to --advisory-db)" },
+                "include_dependencies": { "type": "boolean", "default": false, "description": "Also return the normalized dependency list" }
+            },
+            "required": ["paths"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Supply Chain Scan", false)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let paths: Vec<PathBuf> = array_arg(&args, "paths")?;
+        let advisory_db = args.get("advisory_db").and_then(|v| v.as_str()).map(PathBuf::from)
+            .or_else(|| ctx.config.advisory_db.clone())
+            .ok_or_else(|| invalid_params("No advisory database configured; pass advisory_db or start sidero with --advisory-db"))?;
+        let include_dependencies = args.get("include_dependencies").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let report = tokio::task::spawn_blocking(move || SupplyChainScanner::scan(&paths, &advisory_db, include_dependencies))
+            .await
+            .map_err(internal_error)?
+            .map_err(internal_error)?;
+        Ok(json_result(&report))
+    }
+}
+
+pub struct SecretsScan;
+
+#[async_trait]
+impl ToolHandler for SecretsScan {
+    fn name(&self) -> &str {
+        "semgrep_secrets_scan"
+    }
+
+    fn description(&self) -> &str {
+        "Scan files for leaked credentials with built-in key-format and entropy detectors plus optional local secrets rules. Matched values are redacted."
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "Files or directories to scan" },
+                "rules_dir": { "type": "string", "description": "Directory of secrets Semgrep rules (defaults to --secrets-rules)" },
+                "builtin": { "type": "boolean", "default": true, "description": "Run the built-in detectors" },
+                "min_entropy": { "type": "number", "default": 4.5, "description": "Entropy threshold (bits/char) for generic high-entropy strings" }
+            },
+            "required": ["paths"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Secrets Scan", false)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let paths: Vec<PathBuf> = array_arg(&args, "paths")?;
+        let options = SecretsOptions {
+            rules_dir: args.get("rules_dir").and_then(|v| v.as_str()).map(PathBuf::from).or_else(|| ctx.config.secrets_rules.clone()),
+            builtin: args.get("builtin").and_then(|v| v.as_bool()).unwrap_or(true),
+            min_entropy: args.get("min_entropy").and_then(|v| v.as_f64()).unwrap_or(4.5),
+        };
+        if !options.builtin && options.rules_dir.is_none() {
+            return Err(invalid_params("Nothing to run: builtin is false and no rules_dir is configured"));
+        }
+
+        let report = SecretsScanner::scan(ctx.semgrep, paths, options).await.map_err(internal_error)?;
+        Ok(json_result(&report))
+    }
+}
+
+pub struct AbstractSyntaxTree;
+
+#[async_trait]
+impl ToolHandler for AbstractSyntaxTree {
+    fn name(&self) -> &str {
+        "get_abstract_syntax_tree"
+    }
+
+    fn description(&self) -> &str {
+        "Get the AST of a code snippet"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "code": { "type": "string", "description": "Code content" },
+                "language": { "type": "string", "description": "Language of the code" }
+            },
+            "required": ["code", "language"]
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Abstract Syntax Tree", false)
+    }
+
+    async fn call(&self, _ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let code = required_str(&args, "code")?.to_string();
+        let lang = required_str(&args, "language")?.to_string();
+        let result = SemgrepWrapper::dump_ast(code, lang).await.map_err(internal_error)?;
+        Ok(json_result(&result))
+    }
+}
+
+pub struct Version;
+
+#[async_trait]
+impl ToolHandler for Version {
+    fn name(&self) -> &str {
+        "get_version"
+    }
+
+    fn description(&self) -> &str {
+        "Get Semgrep version"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Semgrep Version", false)
+    }
+
+    async fn call(&self, _ctx: &ToolContext<'_>, _args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let version = SemgrepWrapper::get_version().await.map_err(internal_error)?;
+        Ok(text_result(version))
+    }
+}
+
+pub struct SupportedLanguages;
+
+#[async_trait]
+impl ToolHandler for SupportedLanguages {
+    fn name(&self) -> &str {
+        "supported_languages"
+    }
+
+    fn description(&self) -> &str {
+        "List supported languages"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Supported Languages", false)
+    }
+
+    async fn call(&self, _ctx: &ToolContext<'_>, _args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let langs = SemgrepWrapper::get_supported_languages().await.map_err(internal_error)?;
+        Ok(text_result(langs.join(", ")))
+    }
+}
+
+/// Tool output for a stored scan: Semgrep's JSON plus the scan's resource handle.
+fn scan_tool_output(record: &ScanRecord) -> Value {
+    let mut output = record.output.clone();
+    if let Some(obj) = output.as_object_mut() {
+        obj.insert("scan_id".to_string(), Value::String(record.id.clone()));
+        obj.insert("scan_uri".to_string(), Value::String(record.uri()));
+    }
+    output
+}
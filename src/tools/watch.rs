@@ -0,0 +1,93 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use crate::handler::internal_error;
+use crate::protocol::{CallToolResult, JsonRpcError, ToolAnnotations};
+use crate::rule_packs::resolve_config;
+use crate::scan_store::ScanSource;
+use super::{array_arg, invalid_params, json_result, read_only, ToolContext, ToolHandler};
+
+pub struct StartWatch;
+
+#[async_trait]
+impl ToolHandler for StartWatch {
+    fn name(&self) -> &str {
+        "start_watch"
+    }
+
+    fn description(&self) -> &str {
+        "Watch workspace roots and re-scan files as they change, reporting new and resolved findings as log messages and through the semgrep://watch/{watch_id} resource"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "Workspace roots to watch (defaults to the current directory)" },
+                "config": { "type": "string", "description": "Rule configuration, as for semgrep_scan" },
+                "rule": { "type": "string", "description": "YAML rule content to watch with instead of a config" }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        // A watch keeps a watcher and re-scans running until it is stopped, so `--read-only`
+        // leaves it out. Registry configs are downloaded from semgrep.dev.
+        ToolAnnotations {
+            title: Some("Start Watch".to_string()),
+            readOnlyHint: Some(false),
+            destructiveHint: Some(false),
+            idempotentHint: Some(false),
+            openWorldHint: Some(true),
+        }
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let paths: Vec<String> = match args.get("paths") {
+            Some(v) if !v.is_null() => array_arg(&args, "paths")?,
+            _ => vec![".".to_string()],
+        };
+        let config = args.get("config").and_then(|v| v.as_str());
+        let source = match args.get("rule").and_then(|v| v.as_str()) {
+            Some(_) if config.is_some() => return Err(invalid_params("Pass either config or rule, not both")),
+            Some(rule) => ScanSource::CustomRule { rule: rule.to_string() },
+            None => ScanSource::Config { config: resolve_config(config, ctx.config).map_err(|e| invalid_params(e.to_string()))? },
+        };
+
+        // Writing the disk cache must not look like a change to the workspace.
+        let ignored: Vec<_> = ctx.config.cache_dir.iter().cloned().collect();
+        let watch = ctx.watches.start(source, &paths, &ignored).await.map_err(internal_error)?;
+        Ok(json_result(&watch))
+    }
+}
+
+pub struct StopWatch;
+
+#[async_trait]
+impl ToolHandler for StopWatch {
+    fn name(&self) -> &str {
+        "stop_watch"
+    }
+
+    fn description(&self) -> &str {
+        "Stop a watch started with start_watch, or every watch if no ID is given"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "watch_id": { "type": "string", "description": "ID returned by start_watch" }
+            }
+        })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        read_only("Stop Watch", false)
+    }
+
+    async fn call(&self, ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let watch_id = args.get("watch_id").and_then(|v| v.as_str());
+        let stopped = ctx.watches.stop(watch_id).map_err(|e| invalid_params(e.to_string()))?;
+        Ok(json_result(&json!({ "stopped": stopped })))
+    }
+}
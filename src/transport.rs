@@ -0,0 +1,251 @@
+//! Line-delimited JSON-RPC transport. MCP's stdio transport is this over stdin/stdout.
+
+use anyhow::Result;
+use futures::future::join_all;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::task::{AbortHandle, JoinHandle, JoinSet};
+use tracing::{debug, error, info, warn};
+use crate::handler::Handler;
+use crate::notifier::Notifier;
+use crate::protocol::{self, JsonRpcError, JsonRpcErrorResponse, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse, RequestId};
+use crate::server::Server;
+
+/// Requests still being handled, by ID, so `notifications/cancelled` can stop them.
+type InFlight = Arc<Mutex<HashMap<RequestId, AbortHandle>>>;
+
+/// Serves MCP over stdin/stdout until stdin closes or the process is signalled.
+pub async fn serve_stdio(server: Server) -> Result<()> {
+    serve(server, tokio::io::stdin(), tokio::io::stdout(), shutdown_signal()).await
+}
+
+/// Serves one MCP session over a pair of byte streams, one JSON-RPC message per line,
+/// until the input closes or `shutdown` resolves.
+///
+/// Requests run concurrently, so a long scan does not hold up `ping` or anything else, and
+/// responses go out as they finish. `initialize` and notifications are handled in order
+/// before the next message is read. A request named by `notifications/cancelled` is
+/// stopped and gets no response. When the input closes, requests still running are
+/// answered before this returns.
+pub async fn serve<R, W>(server: Server, reader: R, writer: W, shutdown: impl Future<Output = ()>) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let mut reader = BufReader::new(reader);
+
+    // All output, including server-initiated notifications, is serialized through one writer task.
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<JsonRpcMessage>();
+    let writer_task = tokio::spawn(async move {
+        let mut writer = writer;
+        while let Some(msg) = rx.recv().await {
+            if let Err(e) = send_message(&mut writer, &msg).await {
+                error!("Failed to write message: {}", e);
+                break;
+            }
+        }
+    });
+    let notifier = Notifier::new(tx);
+    let (handler, client_log) = server.into_handler(notifier.clone());
+    let handler = Arc::new(handler);
+    let in_flight = InFlight::default();
+    let mut tasks = JoinSet::new();
+
+    let mut line = String::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        line.clear();
+        let bytes_read = tokio::select! {
+            read = reader.read_line(&mut line) => read?,
+            _ = &mut shutdown => {
+                info!("Received shutdown signal");
+                for (_, request) in in_flight.lock().unwrap().drain() {
+                    request.abort();
+                }
+                tasks.abort_all();
+                break;
+            }
+        };
+        if bytes_read == 0 {
+            info!("Input closed, shutting down");
+            break;
+        }
+        // Reap finished requests so the set does not grow over a long session.
+        while tasks.try_join_next().is_some() {}
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let value = match serde_json::from_str::<Value>(trimmed) {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Failed to parse JSON: {}", e);
+                notifier.send(error_response(None, -32700, "Parse error".to_string()));
+                continue;
+            }
+        };
+
+        match value {
+            Value::Array(items) => {
+                let (handler, in_flight, notifier) = (handler.clone(), in_flight.clone(), notifier.clone());
+                tasks.spawn(async move {
+                    if let Some(response) = handle_batch(&handler, &in_flight, items).await {
+                        notifier.send(response);
+                    }
+                });
+            }
+            value => match JsonRpcMessage::classify(value) {
+                Ok(JsonRpcMessage::Request(req)) if req.method != "initialize" => {
+                    let request = spawn_request(&handler, &in_flight, req);
+                    let notifier = notifier.clone();
+                    tasks.spawn(async move {
+                        if let Ok(response) = request.await {
+                            notifier.send(response);
+                        }
+                    });
+                }
+                message => {
+                    if let Some(response) = handle_classified(&handler, &in_flight, message, false).await {
+                        notifier.send(response);
+                    }
+                }
+            },
+        }
+    }
+
+    // Answer what is still running, stop background tasks, and let the writer drain
+    // whatever is still queued.
+    while tasks.join_next().await.is_some() {}
+    drop(handler);
+    client_log.shutdown();
+    drop(notifier);
+    let _ = writer_task.await;
+
+    Ok(())
+}
+
+/// Runs a request as its own task, registered in `in_flight` until it finishes.
+fn spawn_request(handler: &Arc<Handler>, in_flight: &InFlight, req: JsonRpcRequest) -> JoinHandle<JsonRpcMessage> {
+    let id = req.id.clone();
+    let handler = handler.clone();
+    let (registry, done) = (in_flight.clone(), id.clone());
+    // Held across the spawn so the task cannot deregister before it is registered.
+    let mut requests = in_flight.lock().unwrap();
+    let task = tokio::spawn(async move {
+        let response = respond(&handler, req).await;
+        registry.lock().unwrap().remove(&done);
+        response
+    });
+    requests.insert(id, task.abort_handle());
+    task
+}
+
+/// Stops the request a `notifications/cancelled` names, if it is still running.
+fn cancel(in_flight: &InFlight, params: Option<&Value>) {
+    let Some(id) = params.and_then(|p| p.get("requestId")).and_then(|id| serde_json::from_value::<RequestId>(id.clone()).ok()) else { return };
+    if let Some(request) = in_flight.lock().unwrap().remove(&id) {
+        request.abort();
+        info!("Cancelled request {:?}", id);
+    }
+}
+
+async fn respond(handler: &Handler, req: JsonRpcRequest) -> JsonRpcMessage {
+    let id = req.id.clone();
+    match handler.handle_request(req).await {
+        Ok(result) => JsonRpcMessage::Response(JsonRpcResponse { jsonrpc: "2.0".to_string(), id, result }),
+        Err(err) => JsonRpcMessage::Error(JsonRpcErrorResponse { jsonrpc: "2.0".to_string(), id: Some(id), error: err }),
+    }
+}
+
+/// Handles one classified message inline, returning the response to send (notifications get none).
+async fn handle_classified(handler: &Handler, in_flight: &InFlight, message: Result<JsonRpcMessage, protocol::InvalidMessage>, in_batch: bool) -> Option<JsonRpcMessage> {
+    match message {
+        Ok(JsonRpcMessage::Request(req)) => {
+            if in_batch && req.method == "initialize" {
+                return Some(error_response(Some(req.id), -32600, "initialize must not be part of a batch".to_string()));
+            }
+            Some(respond(handler, req).await)
+        }
+        Ok(JsonRpcMessage::Notification(notif)) => {
+            if notif.method == "notifications/cancelled" {
+                cancel(in_flight, notif.params.as_ref());
+            }
+            handler.handle_notification(notif);
+            None
+        }
+        // The server never sends requests, so there are no responses to match up.
+        Ok(message) => {
+            debug!("Ignoring unexpected response from client: {:?}", message);
+            None
+        }
+        Err(invalid) => {
+            warn!("Invalid JSON-RPC message: {}", invalid.reason);
+            Some(error_response(invalid.id, -32600, format!("Invalid Request: {}", invalid.reason)))
+        }
+    }
+}
+
+/// Handles a JSON-RPC batch concurrently. The reply is a batch of the responses, leaving out
+/// notifications and cancelled requests; a batch with nothing to answer gets no reply at all.
+async fn handle_batch(handler: &Arc<Handler>, in_flight: &InFlight, items: Vec<Value>) -> Option<JsonRpcMessage> {
+    if items.is_empty() {
+        return Some(error_response(None, -32600, "Invalid Request: empty batch".to_string()));
+    }
+    if let Some(version) = handler.protocol_version().filter(|v| !protocol::supports_batching(v)) {
+        return Some(error_response(None, -32600, format!("Batches are not supported in protocol version {}", version)));
+    }
+
+    let members = items.into_iter().map(|item| async move {
+        match JsonRpcMessage::classify(item) {
+            Ok(JsonRpcMessage::Request(req)) if req.method != "initialize" => spawn_request(handler, in_flight, req).await.ok(),
+            message => handle_classified(handler, in_flight, message, true).await,
+        }
+    });
+    let responses: Vec<JsonRpcMessage> = join_all(members).await.into_iter().flatten().collect();
+    (!responses.is_empty()).then_some(JsonRpcMessage::Batch(responses))
+}
+
+fn error_response(id: Option<RequestId>, code: i32, message: String) -> JsonRpcMessage {
+    JsonRpcMessage::Error(JsonRpcErrorResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        error: JsonRpcError { code, message, data: None },
+    })
+}
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+async fn send_message<W: AsyncWrite + Unpin>(writer: &mut W, msg: &JsonRpcMessage) -> Result<()> {
+    let json = serde_json::to_string(msg)?;
+    writer.write_all(json.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,339 @@
+use anyhow::{bail, Context, Result};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use crate::client_log::{ClientLog, LogLevel};
+use crate::fs_walk::{self, SKIPPED_DIRS};
+use crate::notifier::Notifier;
+use crate::protocol::Resource;
+use crate::scan_store::{now, ScanSource};
+use crate::semgrep_wrapper::SemgrepWrapper;
+use crate::subscriptions::Subscribers;
+
+/// Quiet period after the last file event before changed files are re-scanned.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches one session may run at once.
+const MAX_WATCHES: usize = 8;
+
+/// Logger name of the change notifications.
+const LOGGER: &str = "sidero::watch";
+
+/// Runs the watches started with `start_watch`.
+///
+/// Each watch keeps a live finding set for its roots. A background task re-scans files as
+/// they change and reports new and resolved findings as a log message and, to subscribers of
+/// `semgrep://watch/{id}`, as `notifications/resources/updated`.
+pub struct WatchManager {
+    semgrep: SemgrepWrapper,
+    notifier: Notifier,
+    client_log: ClientLog,
+    subscribers: Subscribers,
+    watches: Mutex<HashMap<String, Watch>>,
+    next_id: AtomicU64,
+}
+
+struct Watch {
+    state: Arc<Mutex<WatchState>>,
+    task: JoinHandle<()>,
+}
+
+struct WatchState {
+    id: String,
+    source: ScanSource,
+    roots: Vec<PathBuf>,
+    findings: Vec<Finding>,
+    rescans: u64,
+    /// Unix timestamp (seconds) of the last scan.
+    updated_at: u64,
+}
+
+struct Finding {
+    /// Rule, file, and matched code, so a finding keeps its identity when lines above it move.
+    key: String,
+    result: Value,
+}
+
+impl WatchState {
+    fn uri(&self) -> String {
+        watch_uri(&self.id)
+    }
+
+    fn summary(&self) -> Value {
+        json!({
+            "watch_id": self.id,
+            "uri": self.uri(),
+            "source": self.source,
+            "roots": self.roots,
+            "findings": self.findings.len(),
+            "rescans": self.rescans,
+            "updated_at": self.updated_at,
+        })
+    }
+
+    /// The live finding set, as served from `semgrep://watch/{id}`.
+    fn to_json(&self) -> Value {
+        let mut json = self.summary();
+        json["results"] = Value::Array(self.findings.iter().map(|f| f.result.clone()).collect());
+        json
+    }
+}
+
+impl WatchManager {
+    pub fn new(semgrep: SemgrepWrapper, notifier: Notifier, client_log: ClientLog, subscribers: Subscribers) -> Self {
+        Self { semgrep, notifier, client_log, subscribers, watches: Mutex::new(HashMap::new()), next_id: AtomicU64::new(1) }
+    }
+
+    /// Scans `roots` to seed the finding set and starts watching them. Changes under
+    /// `ignored` (such as the scan cache dir) never trigger a re-scan.
+    pub async fn start(&self, source: ScanSource, roots: &[String], ignored: &[PathBuf]) -> Result<Value> {
+        if self.watches.lock().unwrap().len() >= MAX_WATCHES {
+            bail!("At most {} watches can run at once; stop one with stop_watch", MAX_WATCHES);
+        }
+        let roots = roots
+            .iter()
+            .map(|root| std::fs::canonicalize(root).with_context(|| format!("Cannot watch {}", root)))
+            .collect::<Result<Vec<PathBuf>>>()?;
+        let ignored: Vec<PathBuf> = ignored.iter().filter_map(|p| std::fs::canonicalize(p).ok()).collect();
+
+        let output = source.run(&self.semgrep, roots.iter().map(|r| r.display().to_string()).collect()).await?;
+        let id = format!("w{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let state = Arc::new(Mutex::new(WatchState {
+            id: id.clone(),
+            source,
+            roots: roots.clone(),
+            findings: findings(&output),
+            rescans: 0,
+            updated_at: now(),
+        }));
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let filter_roots = roots.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else { return };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) || matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_))) {
+                return;
+            }
+            for path in event.paths {
+                if is_watched(&path, &filter_roots, &ignored) {
+                    let _ = tx.send(path);
+                }
+            }
+        })
+        .context("Failed to start file watcher")?;
+        for root in &roots {
+            // Files are watched through their parent so editors that save by rename are still seen.
+            let (path, mode) = if root.is_dir() {
+                (root.as_path(), RecursiveMode::Recursive)
+            } else {
+                (root.parent().unwrap_or(root), RecursiveMode::NonRecursive)
+            };
+            watcher.watch(path, mode).with_context(|| format!("Failed to watch {}", path.display()))?;
+        }
+
+        let task = tokio::spawn(run_watch(state.clone(), rx, watcher, self.semgrep.clone(), self.notifier.clone(), self.client_log.clone(), self.subscribers.clone()));
+        let summary = state.lock().unwrap().summary();
+        info!("Started watch {} on {}", id, roots.iter().map(|r| r.display().to_string()).collect::<Vec<_>>().join(", "));
+        self.watches.lock().unwrap().insert(id, Watch { state, task });
+        Ok(summary)
+    }
+
+    /// Stops the watch `id`, or every watch when `id` is `None`, returning the stopped IDs.
+    pub fn stop(&self, id: Option<&str>) -> Result<Vec<String>> {
+        let mut watches = self.watches.lock().unwrap();
+        let ids: Vec<String> = match id {
+            Some(id) if watches.contains_key(id) => vec![id.to_string()],
+            Some(id) => bail!("No watch with ID {}", id),
+            None => watches.keys().cloned().collect(),
+        };
+        for id in &ids {
+            if let Some(watch) = watches.remove(id) {
+                watch.task.abort();
+                info!("Stopped watch {}", id);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// The live finding set of watch `id`.
+    pub fn get(&self, id: &str) -> Option<Value> {
+        self.watches.lock().unwrap().get(id).map(|watch| watch.state.lock().unwrap().to_json())
+    }
+
+    /// A resource for every running watch.
+    pub fn resources(&self) -> Vec<Resource> {
+        let watches = self.watches.lock().unwrap();
+        let mut resources: Vec<Resource> = watches
+            .values()
+            .map(|watch| {
+                let state = watch.state.lock().unwrap();
+                Resource {
+                    uri: state.uri(),
+                    name: format!("Semgrep Watch {}", state.id),
+                    description: Some(format!(
+                        "{} live findings in {}",
+                        state.findings.len(),
+                        state.roots.iter().map(|r| r.display().to_string()).collect::<Vec<_>>().join(", ")
+                    )),
+                    mimeType: Some("application/json".to_string()),
+                }
+            })
+            .collect();
+        resources.sort_by(|a, b| a.uri.cmp(&b.uri));
+        resources
+    }
+}
+
+impl Drop for WatchManager {
+    fn drop(&mut self) {
+        for (_, watch) in self.watches.lock().unwrap().drain() {
+            watch.task.abort();
+        }
+    }
+}
+
+pub fn watch_uri(id: &str) -> String {
+    format!("semgrep://watch/{}", id)
+}
+
+fn is_watched(path: &Path, roots: &[PathBuf], ignored: &[PathBuf]) -> bool {
+    if ignored.iter().any(|dir| path.starts_with(dir)) {
+        return false;
+    }
+    roots.iter().any(|root| {
+        path.strip_prefix(root)
+            .map(|rel| !rel.components().any(|c| SKIPPED_DIRS.iter().any(|skip| c.as_os_str() == *skip)))
+            .unwrap_or(false)
+    })
+}
+
+async fn run_watch(
+    state: Arc<Mutex<WatchState>>,
+    mut rx: mpsc::UnboundedReceiver<PathBuf>,
+    watcher: RecommendedWatcher,
+    semgrep: SemgrepWrapper,
+    notifier: Notifier,
+    client_log: ClientLog,
+    subscribers: Subscribers,
+) {
+    while let Some(path) = rx.recv().await {
+        let mut changed = HashSet::from([path]);
+        while let Ok(Some(path)) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+            changed.insert(path);
+        }
+
+        // Rescan the roots limited to what changed, so Semgrep's ignore rules still apply.
+        // Deleted files only lose their findings.
+        let (source, roots) = {
+            let state = state.lock().unwrap();
+            (state.source.clone(), state.roots.iter().map(|r| r.display().to_string()).collect())
+        };
+        let existing: Vec<PathBuf> = changed.iter().filter(|p| p.exists()).cloned().collect();
+        let targets = match tokio::task::spawn_blocking(move || {
+            existing.iter().flat_map(|p| fs_walk::semgrep_targets(p)).map(|p| p.display().to_string()).collect::<Vec<_>>()
+        })
+        .await
+        {
+            Ok(targets) => targets,
+            Err(e) => {
+                warn!("Watch re-scan failed: {:#}", e);
+                continue;
+            }
+        };
+        let output = if targets.is_empty() {
+            json!({ "results": [] })
+        } else {
+            match source.rescan(&semgrep, roots, targets).await {
+                Ok(output) => output,
+                Err(e) => {
+                    warn!("Watch re-scan failed: {:#}", e);
+                    continue;
+                }
+            }
+        };
+
+        let fresh = findings(&output);
+        let (uri, update) = {
+            let mut state = state.lock().unwrap();
+            let (previous, kept): (Vec<Finding>, Vec<Finding>) = state
+                .findings
+                .drain(..)
+                .partition(|f| result_path(&f.result).is_some_and(|p| changed.iter().any(|c| p.starts_with(c))));
+            let previous_keys: HashSet<&str> = previous.iter().map(|f| f.key.as_str()).collect();
+            let fresh_keys: HashSet<&str> = fresh.iter().map(|f| f.key.as_str()).collect();
+            let new: Vec<Value> = fresh.iter().filter(|f| !previous_keys.contains(f.key.as_str())).map(|f| compact(&f.result)).collect();
+            let resolved: Vec<Value> = previous.iter().filter(|f| !fresh_keys.contains(f.key.as_str())).map(|f| compact(&f.result)).collect();
+
+            state.findings = kept;
+            state.findings.extend(fresh);
+            state.rescans += 1;
+            state.updated_at = now();
+            let update = (!new.is_empty() || !resolved.is_empty()).then(|| (new, resolved, state.findings.len(), state.id.clone()));
+            (state.uri(), update)
+        };
+        let Some((new, resolved, total, id)) = update else { continue };
+
+        debug!("Watch {}: {} new, {} resolved finding(s), {} in total", id, new.len(), resolved.len(), total);
+        let level = if new.is_empty() { LogLevel::Info } else { LogLevel::Warning };
+        client_log.log(level, LOGGER, json!({ "watch_id": id, "uri": uri, "new": new, "resolved": resolved, "total": total }));
+        if subscribers.contains(&uri) {
+            notifier.notify("notifications/resources/updated", Some(json!({ "uri": uri })));
+        }
+    }
+
+    drop(watcher);
+}
+
+/// The findings in a scan's output, each keyed by what it matched.
+fn findings(output: &Value) -> Vec<Finding> {
+    let results = output.get("results").and_then(|r| r.as_array()).cloned().unwrap_or_default();
+    let mut files: HashMap<PathBuf, Vec<String>> = HashMap::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    results
+        .into_iter()
+        .map(|result| {
+            let path = result_path(&result).unwrap_or_default();
+            let lines = files
+                .entry(path.clone())
+                .or_insert_with(|| std::fs::read_to_string(&path).unwrap_or_default().lines().map(str::to_string).collect());
+            let start = result.pointer("/start/line").and_then(|l| l.as_u64()).unwrap_or(1).max(1) as usize;
+            let end = result.pointer("/end/line").and_then(|l| l.as_u64()).unwrap_or(start as u64).max(start as u64) as usize;
+            let matched = lines.get(start - 1..end.min(lines.len())).unwrap_or_default().iter().map(|l| l.trim()).collect::<Vec<_>>().join("\n");
+
+            let mut hasher = Sha256::new();
+            for part in [result.get("check_id").and_then(|c| c.as_str()).unwrap_or_default(), &path.to_string_lossy(), &matched] {
+                hasher.update(part.as_bytes());
+                hasher.update([0]);
+            }
+            let digest: String = hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect();
+            // Identical matches in one file are told apart by their order.
+            let occurrence = seen.entry(digest.clone()).or_default();
+            *occurrence += 1;
+            Finding { key: format!("{}-{}", digest, occurrence), result }
+        })
+        .collect()
+}
+
+fn result_path(result: &Value) -> Option<PathBuf> {
+    result.get("path").and_then(|p| p.as_str()).map(PathBuf::from)
+}
+
+/// What a change notification says about a finding; the full result is in the watch resource.
+fn compact(result: &Value) -> Value {
+    json!({
+        "check_id": result.get("check_id"),
+        "path": result.get("path"),
+        "line": result.pointer("/start/line"),
+        "severity": result.pointer("/extra/severity"),
+        "message": result.pointer("/extra/message").and_then(|m| m.as_str()).map(str::trim),
+    })
+}
@@ -0,0 +1,110 @@
+//! JSON-RPC batches, which MCP allowed up to protocol version 2025-03-26.
+
+mod common;
+
+use common::{error_code, Server};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn ids(batch: &Value) -> Vec<Value> {
+    let mut ids: Vec<Value> = batch.as_array().expect("batch response").iter().map(|r| r["id"].clone()).collect();
+    ids.sort_by_key(|id| id.to_string());
+    ids
+}
+
+#[test]
+fn mixed_batch_answers_only_the_requests() {
+    let mut server = Server::initialized();
+    server.send(&json!([
+        { "jsonrpc": "2.0", "id": 10, "method": "ping" },
+        { "jsonrpc": "2.0", "method": "notifications/cancelled", "params": { "requestId": 3 } },
+        { "jsonrpc": "2.0", "id": "b", "method": "tools/list" },
+        { "jsonrpc": "2.0", "id": 11, "method": "no/such/method" },
+    ]));
+
+    let response = server.recv_response().expect("batch response");
+    assert_eq!(ids(&response), vec![json!("b"), json!(10), json!(11)]);
+    for item in response.as_array().unwrap() {
+        match item["id"].clone() {
+            id if id == json!(10) => assert_eq!(item["result"], json!({})),
+            id if id == json!(11) => assert_eq!(error_code(item), Some(-32601)),
+            _ => assert!(item["result"]["tools"].is_array()),
+        }
+    }
+}
+
+#[test]
+fn batch_of_notifications_gets_no_response() {
+    let mut server = Server::initialized();
+    server.send(&json!([
+        { "jsonrpc": "2.0", "method": "notifications/cancelled", "params": { "requestId": 1 } },
+        { "jsonrpc": "2.0", "method": "notifications/roots/list_changed" },
+    ]));
+    assert!(server.recv_timeout(Duration::from_millis(500)).is_none());
+
+    // The server is still responsive afterwards.
+    let response = server.request(2, "ping", None);
+    assert_eq!(response["result"], json!({}));
+}
+
+#[test]
+fn empty_batch_is_an_invalid_request() {
+    let mut server = Server::initialized();
+    server.send_raw("[]");
+    let response = server.recv_response().expect("error response");
+    assert!(response.is_object());
+    assert_eq!(error_code(&response), Some(-32600));
+    assert_eq!(response["id"], Value::Null);
+}
+
+#[test]
+fn invalid_batch_members_get_their_own_errors() {
+    let mut server = Server::initialized();
+    server.send(&json!([1, { "jsonrpc": "2.0", "id": 5, "method": "ping" }, { "foo": "bar" }]));
+
+    let response = server.recv_response().expect("batch response");
+    let items = response.as_array().expect("batch response");
+    assert_eq!(items.len(), 3);
+    assert_eq!(items.iter().filter(|r| error_code(r) == Some(-32600)).count(), 2);
+    assert!(items.iter().any(|r| r["id"] == json!(5) && r["result"] == json!({})));
+}
+
+#[test]
+fn initialize_is_not_allowed_in_a_batch() {
+    let mut server = Server::start();
+    server.send(&json!([{
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": common::initialize_params("2024-11-05"),
+    }]));
+
+    let response = server.recv_response().expect("batch response");
+    assert_eq!(error_code(&response[0]), Some(-32600));
+    assert_eq!(response[0]["id"], json!(1));
+}
+
+#[test]
+fn batches_are_rejected_from_2025_06_18() {
+    let mut server = Server::initialized_with("2025-06-18");
+    server.send(&json!([
+        { "jsonrpc": "2.0", "id": 2, "method": "ping" },
+        { "jsonrpc": "2.0", "id": 3, "method": "ping" },
+    ]));
+
+    let response = server.recv_response().expect("error response");
+    assert!(response.is_object());
+    assert_eq!(error_code(&response), Some(-32600));
+}
+
+#[test]
+fn batches_are_accepted_at_2025_03_26() {
+    let mut server = Server::initialized_with("2025-03-26");
+    server.send(&json!([
+        { "jsonrpc": "2.0", "id": 2, "method": "ping" },
+        { "jsonrpc": "2.0", "id": 3, "method": "ping" },
+    ]));
+
+    let response = server.recv_response().expect("batch response");
+    assert_eq!(ids(&response), vec![json!(2), json!(3)]);
+}
@@ -0,0 +1,211 @@
+//! Reusing scan results for unchanged files, run against a stub `semgrep` on PATH.
+
+mod common;
+
+use common::{stub_semgrep, Server, SCANNING_SEMGREP};
+use serde_json::{json, Value};
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A workspace to scan and the stub's scan log for one test.
+struct Workspace {
+    dir: TempDir,
+}
+
+impl Workspace {
+    fn new() -> Self {
+        let workspace = Self { dir: tempfile::tempdir().unwrap() };
+        std::fs::create_dir(workspace.dir.path().join("src")).unwrap();
+        workspace.write("rules.yaml", "rules: []\n");
+        workspace
+    }
+
+    fn server(&self, args: &[&str]) -> Server {
+        let path = stub_semgrep(SCANNING_SEMGREP);
+        let log = self.path("scans.log");
+        Server::start_with(args, &[("PATH", &path), ("STUB_LOG", &log)]).handshake("2024-11-05")
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.dir.path().join(name).display().to_string()
+    }
+
+    fn write(&self, name: &str, contents: &str) {
+        std::fs::write(self.dir.path().join(name), contents).unwrap();
+    }
+
+    /// The targets and includes of each scan the stub ran.
+    fn scans(&self) -> Vec<String> {
+        std::fs::read_to_string(self.path("scans.log")).unwrap_or_default().lines().map(str::to_string).collect()
+    }
+
+    fn scan(&self, server: &mut Server, id: i64) -> Value {
+        let arguments = json!({ "paths": [self.path("src")], "config": self.path("rules.yaml") });
+        self.call(server, id, arguments)
+    }
+
+    fn call(&self, server: &mut Server, id: i64, arguments: Value) -> Value {
+        let response = server.request(id, "tools/call", Some(json!({ "name": "semgrep_scan", "arguments": arguments })));
+        let text = response["result"]["content"][0]["text"].as_str().unwrap_or_else(|| panic!("scan failed: {}", response));
+        serde_json::from_str(text).unwrap()
+    }
+}
+
+fn lines(output: &Value) -> Vec<(String, u64)> {
+    output["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| (Path::new(r["path"].as_str().unwrap()).file_name().unwrap().to_string_lossy().to_string(), r["start"]["line"].as_u64().unwrap()))
+        .collect()
+}
+
+#[test]
+fn unchanged_files_are_not_scanned_again() {
+    let workspace = Workspace::new();
+    workspace.write("src/a.js", "eval(a);\n");
+    workspace.write("src/b.js", "let b = 1;\n");
+    let mut server = workspace.server(&[]);
+
+    let first = workspace.scan(&mut server, 2);
+    assert_eq!(first["cache"], json!({ "hits": 0, "misses": 2 }));
+    assert_eq!(lines(&first), vec![("a.js".to_string(), 1)]);
+
+    let second = workspace.scan(&mut server, 3);
+    assert_eq!(second["cache"], json!({ "hits": 2, "misses": 0 }));
+    assert_eq!(second["results"], first["results"]);
+    assert_eq!(second["paths"]["scanned"].as_array().unwrap().len(), 2);
+    assert_eq!(workspace.scans().len(), 1);
+
+    workspace.write("src/b.js", "let b = 1;\neval(b);\n");
+    let third = workspace.scan(&mut server, 4);
+    assert_eq!(third["cache"], json!({ "hits": 1, "misses": 1 }));
+    assert_eq!(lines(&third), vec![("a.js".to_string(), 1), ("b.js".to_string(), 2)]);
+    // The rescan covers the original root, limited to the changed file.
+    assert_eq!(workspace.scans().last().unwrap(), &format!("{} --include b.js", workspace.path("src")));
+}
+
+#[test]
+fn rescans_keep_ignore_rules() {
+    let workspace = Workspace::new();
+    std::fs::create_dir(workspace.dir.path().join("src/generated")).unwrap();
+    workspace.write("src/.semgrepignore", "generated\n");
+    workspace.write("src/a.js", "eval(a);\n");
+    workspace.write("src/generated/b.js", "eval(b);\n");
+    let mut server = workspace.server(&[]);
+
+    workspace.scan(&mut server, 2);
+    workspace.write("src/generated/b.js", "eval(b);\neval(c);\n");
+    workspace.write("src/a.js", "eval(a);\neval(d);\n");
+    let output = workspace.scan(&mut server, 3);
+    assert_eq!(lines(&output), vec![("a.js".to_string(), 1), ("a.js".to_string(), 2)]);
+}
+
+#[test]
+fn directories_semgrep_scans_are_cached_too() {
+    let workspace = Workspace::new();
+    std::fs::create_dir(workspace.dir.path().join("src/target")).unwrap();
+    workspace.write("src/target/t.js", "eval(t);\n");
+    let mut server = workspace.server(&[]);
+
+    let first = workspace.scan(&mut server, 2);
+    let second = workspace.scan(&mut server, 3);
+    assert_eq!(second["cache"], json!({ "hits": 1, "misses": 0 }));
+    assert_eq!(lines(&second), vec![("t.js".to_string(), 1)]);
+    assert_eq!(second["results"], first["results"]);
+}
+
+#[test]
+fn files_semgrep_ignores_are_not_hashed() {
+    let workspace = Workspace::new();
+    std::fs::create_dir(workspace.dir.path().join("src/node_modules")).unwrap();
+    workspace.write("src/node_modules/dep.js", "let dep = 1;\n");
+    workspace.write("src/a.js", "eval(a);\n");
+    let mut server = workspace.server(&[]);
+
+    let output = workspace.scan(&mut server, 2);
+    assert_eq!(output["cache"], json!({ "hits": 0, "misses": 1 }));
+}
+
+#[test]
+fn cached_scans_keep_the_run_fields() {
+    let workspace = Workspace::new();
+    workspace.write("src/a.js", "eval(a);\n");
+    let mut server = workspace.server(&[]);
+
+    let first = workspace.scan(&mut server, 2);
+    let second = workspace.scan(&mut server, 3);
+    assert_eq!(second["cache"], json!({ "hits": 1, "misses": 0 }));
+    assert_eq!(second["version"], "1.99.0");
+    let fields = |output: &Value| output.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+    assert_eq!(fields(&first), fields(&second));
+}
+
+#[test]
+fn missing_paths_are_passed_to_semgrep() {
+    let workspace = Workspace::new();
+    workspace.write("src/a.js", "eval(a);\n");
+    let mut server = workspace.server(&[]);
+
+    workspace.scan(&mut server, 2);
+    let arguments = json!({ "paths": [workspace.path("src"), workspace.path("missing.js")], "config": workspace.path("rules.yaml") });
+    let response = server.request(3, "tools/call", Some(json!({ "name": "semgrep_scan", "arguments": arguments })));
+    assert!(response.get("error").is_some(), "{}", response);
+    assert_eq!(workspace.scans().len(), 2);
+}
+
+#[test]
+fn registry_configs_are_not_cached() {
+    let workspace = Workspace::new();
+    workspace.write("src/a.js", "eval(a);\n");
+    let mut server = workspace.server(&["--cache-dir", &workspace.path("cache")]);
+
+    let arguments = json!({ "paths": [workspace.path("src")], "config": "p/ci" });
+    let first = workspace.call(&mut server, 2, arguments.clone());
+    assert!(first.get("cache").is_none());
+    workspace.call(&mut server, 3, arguments);
+    assert_eq!(workspace.scans().len(), 2);
+}
+
+#[test]
+fn changed_rules_invalidate_the_cache() {
+    let workspace = Workspace::new();
+    workspace.write("src/a.js", "eval(a);\n");
+    let mut server = workspace.server(&[]);
+
+    workspace.scan(&mut server, 2);
+    workspace.write("rules.yaml", "rules: [] # edited\n");
+    let output = workspace.scan(&mut server, 3);
+    assert_eq!(output["cache"], json!({ "hits": 0, "misses": 1 }));
+    assert_eq!(workspace.scans().len(), 2);
+}
+
+#[test]
+fn disk_cache_survives_a_restart() {
+    let workspace = Workspace::new();
+    workspace.write("src/a.js", "eval(a);\n");
+    let cache_dir = workspace.path("cache");
+
+    let mut server = workspace.server(&["--cache-dir", &cache_dir]);
+    workspace.scan(&mut server, 2);
+    drop(server);
+
+    let mut server = workspace.server(&["--cache-dir", &cache_dir]);
+    let output = workspace.scan(&mut server, 2);
+    assert_eq!(output["cache"], json!({ "hits": 1, "misses": 0 }));
+    assert_eq!(output["version"], "1.99.0");
+    assert_eq!(lines(&output), vec![("a.js".to_string(), 1)]);
+    assert_eq!(workspace.scans().len(), 1);
+}
+
+#[test]
+fn cache_can_be_turned_off() {
+    let workspace = Workspace::new();
+    workspace.write("src/a.js", "eval(a);\n");
+    let mut server = workspace.server(&["--no-scan-cache"]);
+
+    let first = workspace.scan(&mut server, 2);
+    assert!(first.get("cache").is_none());
+    workspace.scan(&mut server, 3);
+    assert_eq!(workspace.scans().len(), 2);
+}
@@ -0,0 +1,78 @@
+//! One-shot subcommands, run against a stub `semgrep` on PATH.
+
+mod common;
+
+use common::stub_semgrep;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// A `semgrep` that reports one finding in the last path it is given.
+const FAKE_SEMGREP: &str = r#"#!/bin/sh
+case "$1" in
+  --version) echo "1.99.0"; exit 0;;
+  --dump-ast) echo '{"ast":"stub"}'; exit 0;;
+esac
+last=""; for a in "$@"; do last="$a"; done
+echo "{\"results\":[{\"check_id\":\"stub.rule\",\"path\":\"$last\",\"start\":{\"line\":3,\"col\":1},\"end\":{\"line\":3,\"col\":5},\"extra\":{\"message\":\"stub finding\",\"severity\":\"ERROR\"}}],\"errors\":[]}"
+"#;
+
+fn sidero(home: &TempDir, args: &[&str]) -> Output {
+    let path = stub_semgrep(FAKE_SEMGREP);
+    Command::new(env!("CARGO_BIN_EXE_sidero"))
+        .args(args)
+        .env("PATH", path)
+        .env("HOME", home.path())
+        .env_remove("SEMGREP_APP_TOKEN")
+        .env_remove("SEMGREP_SETTINGS_FILE")
+        .output()
+        .expect("run sidero")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn write(dir: &Path, name: &str, contents: &str) -> String {
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn scan_prints_one_line_per_finding() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = write(dir.path(), "app.py", "import os\n\nos.system(cmd)\n");
+    let output = sidero(&dir, &["scan", "--offline", "--config", &target, &target]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), format!("{}:3: [ERROR] stub.rule: stub finding\n1 finding\n", target));
+}
+
+#[test]
+fn scan_json_prints_the_tool_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = write(dir.path(), "app.py", "x = 1\n");
+    let rule = write(dir.path(), "rule.yaml", "rules: []\n");
+    let output = sidero(&dir, &["scan", "--rule", &rule, "--json", &target]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(json["results"][0]["check_id"], "stub.rule");
+    assert_eq!(json["scan_id"], "1");
+}
+
+#[test]
+fn ast_reads_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = write(dir.path(), "main.rs", "fn main() {}\n");
+    let output = sidero(&dir, &["ast", "--lang", "rust", &source]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("\"stub\""));
+}
+
+#[test]
+fn findings_without_a_token_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = sidero(&dir, &["findings", "--severity", "high"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SEMGREP_APP_TOKEN"));
+}
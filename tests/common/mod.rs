@@ -0,0 +1,244 @@
+//! Drives the `sidero` binary over stdio, the way an MCP client does.
+
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A `semgrep scan` that reports every line containing `eval(` and lists the files it scanned.
+/// It honours `--include` file-name patterns and the names listed in a target directory's
+/// `.semgrepignore`. The targets and includes of every scan are appended to `$STUB_LOG`, one
+/// scan per line.
+pub const SCANNING_SEMGREP: &str = r#"#!/usr/bin/env python3
+import fnmatch, json, os, sys
+
+if sys.argv[1:] == ["--version"]:
+    print("1.99.0")
+    sys.exit(0)
+
+args = sys.argv[2:]
+targets, includes = [], []
+i = 0
+while i < len(args):
+    if args[i] in ("--config", "--include"):
+        if args[i] == "--include":
+            includes.append(args[i + 1])
+        i += 2
+        continue
+    if not args[i].startswith("--"):
+        targets.append(args[i])
+    i += 1
+
+files = []
+for target in targets:
+    if os.path.isdir(target):
+        ignore = os.path.join(target, ".semgrepignore")
+        ignored = open(ignore).read().split() if os.path.exists(ignore) else []
+        for root, dirs, names in os.walk(target):
+            dirs[:] = [d for d in dirs if d not in ignored]
+            files.extend(os.path.join(root, name) for name in sorted(names) if name not in ignored)
+    else:
+        files.append(target)
+if includes:
+    files = [f for f in files if any(fnmatch.fnmatch(os.path.basename(f), pattern) for pattern in includes)]
+
+with open(os.environ["STUB_LOG"], "a") as log:
+    log.write(" ".join(targets + ["--include " + pattern for pattern in includes]) + "\n")
+
+results = []
+for path in files:
+    for n, line in enumerate(open(path).read().splitlines()):
+        if "eval(" in line:
+            results.append({"check_id": "stub.eval", "path": path, "start": {"line": n + 1, "col": 1}, "end": {"line": n + 1, "col": len(line) + 1}, "extra": {"message": "eval found", "severity": "ERROR"}})
+print(json.dumps({"version": "1.99.0", "results": results, "errors": [], "paths": {"scanned": files}}))
+"#;
+
+/// A `PATH` whose first entry holds a `semgrep` running `script`. Each script is written once,
+/// so no test execs it while another is still writing it.
+pub fn stub_semgrep(script: &'static str) -> String {
+    static BINS: OnceLock<Mutex<HashMap<&'static str, TempDir>>> = OnceLock::new();
+    let mut bins = BINS.get_or_init(Default::default).lock().unwrap();
+    let bin = bins.entry(script).or_insert_with(|| {
+        let bin = tempfile::tempdir().unwrap();
+        let semgrep = bin.path().join("semgrep");
+        std::fs::write(&semgrep, script).unwrap();
+        std::fs::set_permissions(&semgrep, std::fs::Permissions::from_mode(0o755)).unwrap();
+        bin
+    });
+    format!("{}:{}", bin.path().display(), std::env::var("PATH").unwrap_or_default())
+}
+
+pub struct Server {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: Receiver<String>,
+    /// Isolated HOME / XDG directories, kept alive for the server's lifetime.
+    home: TempDir,
+}
+
+impl Server {
+    /// Starts a server whose user directories point at an empty temp dir.
+    pub fn start() -> Self {
+        Self::start_with(&[], &[])
+    }
+
+    /// Like [`Server::start`], with extra command-line arguments and environment variables.
+    pub fn start_with(args: &[&str], envs: &[(&str, &str)]) -> Self {
+        let home = tempfile::tempdir().expect("create temp home");
+        let mut child = Command::new(env!("CARGO_BIN_EXE_sidero"))
+            .args(args)
+            .env("HOME", home.path())
+            .env("XDG_CONFIG_HOME", home.path().join("config"))
+            .env("XDG_DATA_HOME", home.path().join("data"))
+            .env_remove("SEMGREP_APP_TOKEN")
+            .env_remove("SEMGREP_SETTINGS_FILE")
+            .envs(envs.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("spawn sidero");
+
+        let stdout = child.stdout.take().unwrap();
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let stdin = child.stdin.take();
+        Self { child, stdin, lines, home }
+    }
+
+    /// Starts a server and completes the initialize handshake.
+    pub fn initialized() -> Self {
+        Self::initialized_with("2024-11-05")
+    }
+
+    /// Starts a server and completes the initialize handshake at the given protocol version.
+    pub fn initialized_with(protocol_version: &str) -> Self {
+        Self::start().handshake(protocol_version)
+    }
+
+    /// Completes the initialize handshake on a started server.
+    pub fn handshake(mut self, protocol_version: &str) -> Self {
+        let response = self.request(1, "initialize", initialize_params(protocol_version));
+        assert!(response.get("result").is_some(), "initialize failed: {}", response);
+        self.notify("notifications/initialized", None);
+        self
+    }
+
+    /// The server's temporary HOME directory.
+    pub fn home(&self) -> &std::path::Path {
+        self.home.path()
+    }
+
+    pub fn send_raw(&mut self, line: &str) {
+        let stdin = self.stdin.as_mut().expect("stdin already closed");
+        stdin.write_all(line.as_bytes()).unwrap();
+        stdin.write_all(b"\n").unwrap();
+        stdin.flush().unwrap();
+    }
+
+    pub fn send(&mut self, message: &Value) {
+        self.send_raw(&message.to_string());
+    }
+
+    /// Sends a request and waits for the response with the same id, skipping notifications.
+    pub fn request(&mut self, id: i64, method: &str, params: Option<Value>) -> Value {
+        let mut message = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+        self.send(&message);
+        loop {
+            let response = self.recv().unwrap_or_else(|| panic!("no response to {} (id {})", method, id));
+            if response.get("id") == Some(&json!(id)) {
+                return response;
+            }
+        }
+    }
+
+    pub fn notify(&mut self, method: &str, params: Option<Value>) {
+        let mut message = json!({ "jsonrpc": "2.0", "method": method });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+        self.send(&message);
+    }
+
+    /// Next message from the server, or `None` if nothing arrives in time.
+    pub fn recv(&mut self) -> Option<Value> {
+        self.recv_timeout(TIMEOUT)
+    }
+
+    /// Next response or error from the server, skipping notifications such as forwarded log messages.
+    pub fn recv_response(&mut self) -> Option<Value> {
+        loop {
+            let message = self.recv()?;
+            if message.get("method").is_none() {
+                return Some(message);
+            }
+        }
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Value> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Some(serde_json::from_str(&line).unwrap_or_else(|e| panic!("server wrote invalid JSON ({}): {}", e, line))),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// Closes stdin without waiting, so responses still on their way can be read.
+    pub fn close_stdin(&mut self) {
+        drop(self.stdin.take());
+    }
+
+    /// Closes stdin and waits for the server to exit.
+    pub fn close(mut self) -> ExitStatus {
+        drop(self.stdin.take());
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status;
+            }
+            if Instant::now() > deadline {
+                let _ = self.child.kill();
+                panic!("server did not exit after stdin was closed");
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub fn initialize_params(protocol_version: &str) -> Option<Value> {
+    Some(json!({
+        "protocolVersion": protocol_version,
+        "capabilities": {},
+        "clientInfo": { "name": "conformance-test", "version": "1.0.0" }
+    }))
+}
+
+pub fn error_code(response: &Value) -> Option<i64> {
+    response.pointer("/error/code").and_then(|c| c.as_i64())
+}
@@ -0,0 +1,236 @@
+//! MCP lifecycle and protocol conformance, checked against the real binary over stdio.
+
+mod common;
+
+use common::{error_code, initialize_params, stub_semgrep, Server};
+use serde_json::json;
+use std::time::Duration;
+
+/// A `semgrep` whose scans take long enough to overlap other requests.
+const SLOW_SEMGREP: &str = r#"#!/bin/sh
+if [ "$1" = "--version" ]; then echo "1.99.0"; exit 0; fi
+sleep 3
+echo '{"results":[],"errors":[]}'
+"#;
+
+fn slow_server() -> Server {
+    let path = stub_semgrep(SLOW_SEMGREP);
+    Server::start_with(&["--no-scan-cache"], &[("PATH", &path)]).handshake("2024-11-05")
+}
+
+fn scan_request(id: i64) -> serde_json::Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": "semgrep_scan", "arguments": { "paths": ["."], "config": "p/ci" } } })
+}
+
+#[test]
+fn ping_is_answered_before_and_after_initialize() {
+    let mut server = Server::start();
+    let response = server.request(1, "ping", None);
+    assert_eq!(response["result"], json!({}));
+
+    server.request(2, "initialize", initialize_params("2024-11-05"));
+    server.notify("notifications/initialized", None);
+    let response = server.request(3, "ping", None);
+    assert_eq!(response["result"], json!({}));
+}
+
+#[test]
+fn requests_before_initialize_are_rejected() {
+    let mut server = Server::start();
+    let response = server.request(1, "tools/list", None);
+    assert_eq!(error_code(&response), Some(-32600));
+    assert_eq!(response["id"], json!(1));
+}
+
+#[test]
+fn initialize_reports_server_info_and_capabilities() {
+    let mut server = Server::start();
+    let response = server.request(1, "initialize", initialize_params("2024-11-05"));
+    let result = &response["result"];
+    assert_eq!(result["protocolVersion"], "2024-11-05");
+    assert_eq!(result["serverInfo"]["name"], "sidero");
+    for capability in ["tools", "prompts", "resources", "logging", "completions"] {
+        assert!(result["capabilities"].get(capability).is_some(), "missing capability {}", capability);
+    }
+}
+
+#[test]
+fn initialize_negotiates_the_protocol_version() {
+    let mut server = Server::start();
+    let response = server.request(1, "initialize", initialize_params("2025-03-26"));
+    assert_eq!(response["result"]["protocolVersion"], "2025-03-26");
+
+    let mut server = Server::start();
+    let response = server.request(1, "initialize", initialize_params("1999-01-01"));
+    assert_eq!(response["result"]["protocolVersion"], "2025-06-18");
+}
+
+#[test]
+fn second_initialize_is_rejected() {
+    let mut server = Server::initialized();
+    let response = server.request(2, "initialize", initialize_params("2024-11-05"));
+    assert_eq!(error_code(&response), Some(-32600));
+}
+
+#[test]
+fn list_methods_return_arrays_without_further_pages() {
+    let mut server = Server::initialized();
+    for (id, (method, key)) in [
+        ("tools/list", "tools"),
+        ("prompts/list", "prompts"),
+        ("resources/list", "resources"),
+        ("resources/templates/list", "resourceTemplates"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let response = server.request(10 + id as i64, method, Some(json!({})));
+        assert!(response["result"][key].is_array(), "{} did not return a {} array: {}", method, key, response);
+        assert!(response["result"].get("nextCursor").is_none(), "{} returned an unexpected cursor", method);
+    }
+}
+
+#[test]
+fn tools_carry_annotations() {
+    let mut server = Server::initialized();
+    let response = server.request(2, "tools/list", None);
+    for tool in response["result"]["tools"].as_array().unwrap() {
+        let annotations = &tool["annotations"];
+        assert!(annotations["title"].is_string(), "{} has no title", tool["name"]);
+        assert!(annotations["readOnlyHint"].is_boolean(), "{} has no readOnlyHint", tool["name"]);
+        assert!(annotations["openWorldHint"].is_boolean(), "{} has no openWorldHint", tool["name"]);
+    }
+    let delete = response["result"]["tools"].as_array().unwrap().iter().find(|t| t["name"] == "delete_rule").unwrap();
+    assert_eq!(delete["annotations"]["destructiveHint"], json!(true));
+}
+
+#[test]
+fn unknown_tool_is_method_not_found() {
+    let mut server = Server::initialized();
+    let response = server.request(2, "tools/call", Some(json!({ "name": "no_such_tool", "arguments": {} })));
+    assert_eq!(error_code(&response), Some(-32601));
+}
+
+#[test]
+fn invalid_cursor_is_rejected() {
+    let mut server = Server::initialized();
+    let response = server.request(2, "tools/list", Some(json!({ "cursor": "not-a-cursor" })));
+    assert_eq!(error_code(&response), Some(-32602));
+}
+
+#[test]
+fn unknown_method_is_method_not_found() {
+    let mut server = Server::initialized();
+    let response = server.request(2, "does/not/exist", None);
+    assert_eq!(error_code(&response), Some(-32601));
+    assert_eq!(response["id"], json!(2));
+}
+
+#[test]
+fn string_ids_are_echoed() {
+    let mut server = Server::initialized();
+    server.send(&json!({ "jsonrpc": "2.0", "id": "abc", "method": "ping" }));
+    let response = server.recv_response().expect("no response");
+    assert_eq!(response["id"], json!("abc"));
+    assert_eq!(response["result"], json!({}));
+}
+
+#[test]
+fn requests_are_answered_while_a_scan_runs() {
+    let mut server = slow_server();
+    server.send(&scan_request(2));
+    server.send(&json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" }));
+
+    let first = server.recv_response().expect("ping response");
+    assert_eq!(first["id"], json!(3));
+    let second = server.recv_response().expect("scan response");
+    assert_eq!(second["id"], json!(2));
+    assert!(second.get("result").is_some(), "{}", second);
+}
+
+#[test]
+fn cancelled_requests_get_no_response() {
+    let mut server = slow_server();
+    server.send(&scan_request(2));
+    server.notify("notifications/cancelled", Some(json!({ "requestId": 2, "reason": "user abort" })));
+    let response = server.request(3, "ping", None);
+    assert_eq!(response["result"], json!({}));
+    assert!(server.recv_timeout(Duration::from_secs(4)).is_none());
+}
+
+#[test]
+fn requests_still_running_are_answered_when_input_closes() {
+    let mut server = slow_server();
+    server.send(&scan_request(2));
+    server.send(&json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" }));
+    assert_eq!(server.recv_response().expect("ping response")["id"], json!(3));
+    server.close_stdin();
+    assert_eq!(server.recv_response().expect("scan response")["id"], json!(2));
+    assert!(server.close().success());
+}
+
+#[test]
+fn notifications_get_no_response() {
+    let mut server = Server::initialized();
+    server.notify("notifications/cancelled", Some(json!({ "requestId": 99 })));
+    server.notify("notifications/unknown", None);
+    let response = server.request(2, "ping", None);
+    assert_eq!(response["id"], json!(2));
+    assert!(server.recv_timeout(Duration::from_millis(200)).is_none());
+}
+
+#[test]
+fn malformed_json_is_a_parse_error() {
+    let mut server = Server::initialized();
+    server.send_raw("{not json");
+    let response = server.recv_response().expect("no response");
+    assert_eq!(error_code(&response), Some(-32700));
+    assert_eq!(response["id"], json!(null));
+
+    // The server keeps serving after a parse error.
+    let response = server.request(2, "ping", None);
+    assert_eq!(response["result"], json!({}));
+}
+
+#[test]
+fn invalid_requests_echo_the_recovered_id() {
+    let mut server = Server::initialized();
+    let cases = [
+        (json!({ "jsonrpc": "1.0", "id": 7, "method": "ping" }), json!(7)),
+        (json!({ "id": "abc", "method": "ping" }), json!("abc")),
+        (json!({ "jsonrpc": "2.0", "id": 8 }), json!(8)),
+        (json!({ "jsonrpc": "2.0", "id": 9, "method": 42 }), json!(9)),
+        (json!({ "jsonrpc": "2.0", "id": 10, "method": "ping", "params": "x" }), json!(10)),
+        (json!({ "jsonrpc": "2.0", "id": null, "method": "ping" }), json!(null)),
+        (json!({ "jsonrpc": "2.0", "id": [1], "method": "ping" }), json!(null)),
+        (json!("ping"), json!(null)),
+    ];
+    for (message, id) in cases {
+        server.send(&message);
+        let response = server.recv_response().expect("no response");
+        assert_eq!(error_code(&response), Some(-32600), "for {}", message);
+        assert_eq!(response["id"], id, "for {}", message);
+    }
+}
+
+#[test]
+fn responses_from_the_client_are_ignored() {
+    let mut server = Server::initialized();
+    server.send(&json!({ "jsonrpc": "2.0", "id": 99, "result": {} }));
+    server.send(&json!({ "jsonrpc": "2.0", "id": 98, "error": { "code": -1, "message": "nope" } }));
+    server.send(&json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }));
+    let response = server.recv_response().expect("no response");
+    assert_eq!(response["id"], json!(2));
+}
+
+#[test]
+fn server_exits_cleanly_on_eof() {
+    let server = Server::initialized();
+    assert!(server.close().success());
+}
+
+#[test]
+fn server_exits_cleanly_on_eof_before_initialize() {
+    let server = Server::start();
+    assert!(server.close().success());
+}
@@ -0,0 +1,58 @@
+//! `sidero doctor` and the matching `health_check` tool.
+
+mod common;
+
+use common::Server;
+use serde_json::{json, Value};
+use std::process::Command;
+
+fn doctor(args: &[&str]) -> (Option<i32>, Value) {
+    let home = tempfile::tempdir().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_sidero"))
+        .args(["doctor", "--json", "--offline"])
+        .args(args)
+        .env("HOME", home.path())
+        .env_remove("SEMGREP_APP_TOKEN")
+        .env_remove("SEMGREP_SETTINGS_FILE")
+        .output()
+        .expect("run sidero doctor");
+    let report = serde_json::from_slice(&output.stdout).expect("doctor --json prints a report");
+    (output.status.code(), report)
+}
+
+fn check<'a>(report: &'a Value, name: &str) -> &'a Value {
+    report["checks"].as_array().unwrap().iter().find(|c| c["name"] == name).unwrap_or_else(|| panic!("no {} check", name))
+}
+
+#[test]
+fn doctor_reports_every_check() {
+    let (_, report) = doctor(&[]);
+    for name in ["semgrep", "python_env", "temp_dir", "network", "app_token", "workspace_config"] {
+        check(&report, name);
+    }
+    assert_eq!(check(&report, "network")["status"], "skipped");
+    assert_eq!(check(&report, "temp_dir")["status"], "ok");
+    assert_eq!(check(&report, "app_token")["status"], "warn");
+    assert!(check(&report, "app_token")["fix"].is_string());
+}
+
+#[test]
+fn doctor_fails_on_missing_configured_directories() {
+    let (code, report) = doctor(&["--rules-dir", "/nonexistent/sidero-rules"]);
+    assert_eq!(code, Some(1));
+    assert_eq!(report["healthy"], json!(false));
+    let workspace = check(&report, "workspace_config");
+    assert_eq!(workspace["status"], "fail");
+    assert!(workspace["detail"].as_str().unwrap().contains("--rules-dir"));
+    assert!(workspace["fix"].is_string());
+}
+
+#[test]
+fn health_check_tool_returns_the_report() {
+    let mut server = Server::start_with(&["--offline"], &[]).handshake("2024-11-05");
+    let response = server.request(2, "tools/call", Some(json!({ "name": "health_check", "arguments": {} })));
+    let text = response["result"]["content"][0]["text"].as_str().expect("text content");
+    let report: Value = serde_json::from_str(text).unwrap();
+    assert!(report["healthy"].is_boolean());
+    assert_eq!(check(&report, "network")["status"], "skipped");
+}
@@ -0,0 +1,116 @@
+//! Embedding sidero as a library and extending it through the builder.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use sidero::prompts::PromptTemplate;
+use sidero::protocol::{CallToolResult, Content, JsonRpcError, Resource, ToolAnnotations};
+use sidero::resources::ResourceProvider;
+use sidero::{Config, Server, ToolContext, ToolHandler};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines, ReadHalf, WriteHalf};
+
+struct Echo;
+
+#[async_trait]
+impl ToolHandler for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn description(&self) -> &str {
+        "Echo the message back"
+    }
+
+    fn input_schema(&self) -> Value {
+        json!({ "type": "object", "properties": { "message": { "type": "string" } }, "required": ["message"] })
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations { title: Some("Echo".to_string()), readOnlyHint: Some(true), ..Default::default() }
+    }
+
+    async fn call(&self, _ctx: &ToolContext<'_>, args: Value) -> Result<CallToolResult, JsonRpcError> {
+        let text = args["message"].as_str().unwrap_or_default().to_string();
+        Ok(CallToolResult { content: vec![Content::Text { text }], isError: None })
+    }
+}
+
+struct Policy;
+
+#[async_trait]
+impl ResourceProvider for Policy {
+    fn list(&self) -> Vec<Resource> {
+        vec![Resource {
+            uri: "acme://policy".to_string(),
+            name: "Security Policy".to_string(),
+            description: None,
+            mimeType: Some("text/markdown".to_string()),
+        }]
+    }
+
+    async fn read(&self, uri: &str) -> Option<anyhow::Result<(String, String)>> {
+        (uri == "acme://policy").then(|| Ok(("text/markdown".to_string(), "# No eval".to_string())))
+    }
+}
+
+struct Client {
+    writer: WriteHalf<DuplexStream>,
+    lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+}
+
+impl Client {
+    async fn request(&mut self, id: i64, method: &str, params: Value) -> Value {
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.writer.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        loop {
+            let line = self.lines.next_line().await.unwrap().expect("server closed the stream");
+            let response: Value = serde_json::from_str(&line).unwrap();
+            if response["id"] == json!(id) {
+                return response;
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn builder_adds_tools_prompts_and_resources() {
+    let home = tempfile::tempdir().unwrap();
+    let config = Config {
+        offline: true,
+        prompts_dir: Some(home.path().join("prompts")),
+        rule_library: Some(home.path().join("rules")),
+        ..Default::default()
+    };
+    let prompt = PromptTemplate::parse(
+        "name = \"acme_review\"\ndescription = \"Review against the ACME policy\"\ntemplate = \"Review {{path}}\"\n\n[[arguments]]\nname = \"path\"\nrequired = true\n",
+    )
+    .unwrap();
+    let server = Server::builder(config).tool(Echo).prompt(prompt).resources(Policy).build();
+
+    let (client, server_io) = tokio::io::duplex(64 * 1024);
+    let (server_read, server_write) = tokio::io::split(server_io);
+    let session = tokio::spawn(sidero::transport::serve(server, server_read, server_write, std::future::pending()));
+
+    let (client_read, client_write) = tokio::io::split(client);
+    let mut client = Client { writer: client_write, lines: BufReader::new(client_read).lines() };
+
+    client.request(1, "initialize", json!({ "protocolVersion": "2025-06-18", "capabilities": {}, "clientInfo": { "name": "embed", "version": "1" } })).await;
+
+    let tools = client.request(2, "tools/list", json!({})).await;
+    let names: Vec<&str> = tools["result"]["tools"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap()).collect();
+    assert!(names.contains(&"echo"));
+    assert!(names.contains(&"semgrep_scan"));
+
+    let echoed = client.request(3, "tools/call", json!({ "name": "echo", "arguments": { "message": "hi" } })).await;
+    assert_eq!(echoed["result"]["content"][0]["text"], "hi");
+
+    let prompt = client.request(4, "prompts/get", json!({ "name": "acme_review", "arguments": { "path": "src/" } })).await;
+    assert_eq!(prompt["result"]["messages"][0]["content"]["text"], "Review src/");
+
+    let resources = client.request(5, "resources/list", json!({})).await;
+    assert!(resources["result"]["resources"].as_array().unwrap().iter().any(|r| r["uri"] == "acme://policy"));
+    let policy = client.request(6, "resources/read", json!({ "uri": "acme://policy" })).await;
+    assert_eq!(policy["result"]["contents"][0]["text"], "# No eval");
+
+    client.writer.shutdown().await.unwrap();
+    session.await.unwrap().unwrap();
+}
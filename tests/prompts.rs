@@ -0,0 +1,21 @@
+//! Prompts that embed live context.
+
+mod common;
+
+use common::Server;
+use serde_json::json;
+
+#[test]
+fn rule_prompt_works_without_semgrep() {
+    let empty = tempfile::tempdir().unwrap();
+    let mut server = Server::start_with(&[], &[("PATH", empty.path().to_str().unwrap())]).handshake("2024-11-05");
+
+    let arguments = json!({ "code": "eval(input())", "language": "python" });
+    let response = server.request(2, "prompts/get", Some(json!({ "name": "write_custom_semgrep_rule", "arguments": arguments })));
+    let messages = response["result"]["messages"].as_array().unwrap_or_else(|| panic!("prompt failed: {}", response));
+    assert!(messages[0]["content"]["text"].as_str().unwrap().contains("eval(input())"));
+
+    let ast = messages.iter().find_map(|m| m["content"]["resource"].as_object().filter(|r| r["uri"] == "semgrep://ast/python")).unwrap();
+    assert_eq!(ast["mimeType"], "text/plain");
+    assert!(ast["text"].as_str().unwrap().contains("not available"));
+}
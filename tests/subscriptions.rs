@@ -0,0 +1,56 @@
+//! `resources/subscribe` on scans, run against a stub `semgrep` on PATH.
+
+mod common;
+
+use common::{stub_semgrep, Server, SCANNING_SEMGREP};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+/// Runs `semgrep_scan` on `dir`, returning its output and the URIs reported as updated
+/// while it ran.
+fn scan(server: &mut Server, id: i64, dir: &TempDir, name: &str) -> (Value, Vec<String>) {
+    let arguments = json!({ "paths": [dir.path().join(name)], "config": dir.path().join("rules.yaml") });
+    server.send(&json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": "semgrep_scan", "arguments": arguments } }));
+    let mut updated = Vec::new();
+    loop {
+        let message = server.recv().expect("no response to semgrep_scan");
+        if message["method"] == "notifications/resources/updated" {
+            updated.push(message["params"]["uri"].as_str().unwrap().to_string());
+        } else if message["id"] == id {
+            let text = message["result"]["content"][0]["text"].as_str().unwrap_or_else(|| panic!("scan failed: {}", message));
+            updated.sort();
+            return (serde_json::from_str(text).unwrap(), updated);
+        }
+    }
+}
+
+#[test]
+fn latest_finding_subscriptions_follow_only_scans_that_have_the_finding() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["with", "without"] {
+        std::fs::create_dir(dir.path().join(name)).unwrap();
+    }
+    std::fs::write(dir.path().join("with/a.js"), "eval(a);\n").unwrap();
+    std::fs::write(dir.path().join("without/b.js"), "let b = 1;\n").unwrap();
+    std::fs::write(dir.path().join("rules.yaml"), "rules: []\n").unwrap();
+    let path = stub_semgrep(SCANNING_SEMGREP);
+    let log = dir.path().join("scans.log");
+    let mut server = Server::start_with(&["--no-scan-cache"], &[("PATH", &path), ("STUB_LOG", log.to_str().unwrap())]).handshake("2024-11-05");
+
+    let (output, _) = scan(&mut server, 2, &dir, "with");
+    let fingerprint = output["results"][0]["fingerprint"].as_str().unwrap();
+    let latest = "semgrep://scan/latest".to_string();
+    let finding = format!("semgrep://scan/latest/finding/{}", fingerprint);
+    for (id, uri) in [(3, &latest), (4, &finding)] {
+        let response = server.request(id, "resources/subscribe", Some(json!({ "uri": uri })));
+        assert!(response.get("result").is_some(), "{}", response);
+    }
+
+    let (_, updated) = scan(&mut server, 5, &dir, "without");
+    assert_eq!(updated, vec![latest.clone()]);
+
+    let (_, updated) = scan(&mut server, 6, &dir, "with");
+    let mut expected = vec![latest, finding];
+    expected.sort();
+    assert_eq!(updated, expected);
+}
@@ -0,0 +1,220 @@
+//! Lockfile parsing and advisory matching in `semgrep_supply_chain_scan`.
+
+mod common;
+
+use common::Server;
+use serde_json::{json, Value};
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A project to scan and the advisory database to scan it against.
+struct Project {
+    dir: TempDir,
+    db: TempDir,
+}
+
+impl Project {
+    fn new() -> Self {
+        Self { dir: tempfile::tempdir().unwrap(), db: tempfile::tempdir().unwrap() }
+    }
+
+    fn write(&self, name: &str, contents: &str) {
+        let path = self.dir.path().join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// Adds an advisory for `package` with the given `ranges` events and `versions`.
+    fn advisory(&self, id: &str, ecosystem: &str, package: &str, events: Value, versions: Value) {
+        let advisory = json!({
+            "id": id,
+            "summary": format!("{} summary", id),
+            "affected": [{
+                "package": { "ecosystem": ecosystem, "name": package },
+                "ranges": [{ "type": "ECOSYSTEM", "events": events }],
+                "versions": versions,
+            }],
+        });
+        std::fs::write(self.db.path().join(format!("{}.json", id)), advisory.to_string()).unwrap();
+    }
+
+    fn scan(&self) -> Value {
+        let mut server = Server::initialized();
+        let arguments = json!({
+            "paths": [self.dir.path()],
+            "advisory_db": self.db.path(),
+            "include_dependencies": true,
+        });
+        let response = server.request(2, "tools/call", Some(json!({ "name": "semgrep_supply_chain_scan", "arguments": arguments })));
+        let text = response["result"]["content"][0]["text"].as_str().unwrap_or_else(|| panic!("scan failed: {}", response));
+        serde_json::from_str(text).unwrap()
+    }
+}
+
+/// `(ecosystem, name, version)` of every dependency the scan found.
+fn dependencies(report: &Value) -> Vec<(String, String, String)> {
+    let mut deps: Vec<_> = report["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["ecosystem"].as_str().unwrap().to_string(), d["name"].as_str().unwrap().to_string(), d["version"].as_str().unwrap().to_string()))
+        .collect();
+    deps.sort();
+    deps
+}
+
+fn deps(expected: &[(&str, &str, &str)]) -> Vec<(String, String, String)> {
+    let mut deps: Vec<_> = expected.iter().map(|(e, n, v)| (e.to_string(), n.to_string(), v.to_string())).collect();
+    deps.sort();
+    deps
+}
+
+fn advisory_ids(report: &Value) -> Vec<String> {
+    report["results"].as_array().unwrap().iter().map(|r| r["extra"]["metadata"]["advisory_id"].as_str().unwrap().to_string()).collect()
+}
+
+fn lockfile_dependencies(name: &str, contents: &str) -> Vec<(String, String, String)> {
+    let project = Project::new();
+    project.write(name, contents);
+    dependencies(&project.scan())
+}
+
+#[test]
+fn cargo_lock() {
+    let lock = "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.190\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n[[package]]\nname = \"app\"\nversion = \"0.1.0\"\n";
+    assert_eq!(lockfile_dependencies("Cargo.lock", lock), deps(&[("crates.io", "serde", "1.0.190"), ("crates.io", "app", "0.1.0")]));
+}
+
+#[test]
+fn package_lock_v3_and_v1() {
+    let v3 = json!({
+        "lockfileVersion": 3,
+        "packages": {
+            "": { "name": "app" },
+            "node_modules/lodash": { "version": "4.17.20" },
+            "node_modules/a/node_modules/@scope/b": { "version": "1.0.0" },
+        }
+    });
+    assert_eq!(lockfile_dependencies("package-lock.json", &v3.to_string()), deps(&[("npm", "lodash", "4.17.20"), ("npm", "@scope/b", "1.0.0")]));
+
+    let v1 = json!({
+        "lockfileVersion": 1,
+        "dependencies": { "a": { "version": "1.0.0", "dependencies": { "b": { "version": "2.0.0" } } } }
+    });
+    assert_eq!(lockfile_dependencies("package-lock.json", &v1.to_string()), deps(&[("npm", "a", "1.0.0"), ("npm", "b", "2.0.0")]));
+}
+
+#[test]
+fn yarn_lock() {
+    let lock = "# yarn lockfile v1\n\n\"@babel/core@^7.0.0\", \"@babel/core@^7.1.0\":\n  version \"7.2.0\"\n  resolved \"https://example\"\n\nleft-pad@^1.0.0:\n  version \"1.3.0\"\n";
+    assert_eq!(lockfile_dependencies("yarn.lock", lock), deps(&[("npm", "@babel/core", "7.2.0"), ("npm", "left-pad", "1.3.0")]));
+}
+
+#[test]
+fn python_lockfiles() {
+    let poetry = "[[package]]\nname = \"requests\"\nversion = \"2.25.0\"\n\n[package.dependencies]\nidna = \"*\"\n";
+    assert_eq!(lockfile_dependencies("poetry.lock", poetry), deps(&[("PyPI", "requests", "2.25.0")]));
+
+    let pipfile = json!({ "default": { "django": { "version": "==3.2.0" } }, "develop": { "pytest": { "version": "==7.0.0" } } });
+    assert_eq!(lockfile_dependencies("Pipfile.lock", &pipfile.to_string()), deps(&[("PyPI", "django", "3.2.0"), ("PyPI", "pytest", "7.0.0")]));
+
+    let requirements = "# pinned\nflask[async]==2.0.1 ; python_version >= '3.8'\nnumpy>=1.0\nurllib3==1.26.4  # comment\n";
+    assert_eq!(lockfile_dependencies("requirements-dev.txt", requirements), deps(&[("PyPI", "flask", "2.0.1"), ("PyPI", "urllib3", "1.26.4")]));
+}
+
+#[test]
+fn gemfile_and_composer_locks() {
+    let gemfile = "GEM\n  remote: https://rubygems.org/\n  specs:\n    rack (2.2.3)\n    nokogiri (1.13.0-x86_64-linux)\n      racc (~> 1.4)\n\nPLATFORMS\n  ruby\n";
+    assert_eq!(lockfile_dependencies("Gemfile.lock", gemfile), deps(&[("RubyGems", "rack", "2.2.3"), ("RubyGems", "nokogiri", "1.13.0")]));
+
+    let composer = json!({ "packages": [{ "name": "monolog/monolog", "version": "v2.3.0" }], "packages-dev": [{ "name": "phpunit/phpunit", "version": "9.5.0" }] });
+    assert_eq!(lockfile_dependencies("composer.lock", &composer.to_string()), deps(&[("Packagist", "monolog/monolog", "2.3.0"), ("Packagist", "phpunit/phpunit", "9.5.0")]));
+}
+
+const GO_SUM: &str = "golang.org/x/text v0.3.5 h1:aaa=\ngolang.org/x/text v0.3.5/go.mod h1:bbb=\ngolang.org/x/text v0.3.7 h1:ccc=\ngolang.org/x/text v0.3.7/go.mod h1:ddd=\ngithub.com/old/only v1.0.0/go.mod h1:eee=\n";
+
+#[test]
+fn go_sum_reports_each_module_once_at_the_version_go_mod_requires() {
+    let project = Project::new();
+    project.write("go.sum", GO_SUM);
+    project.write("go.mod", "module example.com/app\n\ngo 1.21\n\nrequire (\n\tgolang.org/x/text v0.3.5 // indirect\n)\n");
+    project.advisory("GO-1", "Go", "golang.org/x/text", json!([{ "introduced": "0" }, { "fixed": "0.3.8" }]), json!([]));
+
+    let report = project.scan();
+    assert_eq!(dependencies(&report), deps(&[("Go", "golang.org/x/text", "0.3.5")]));
+    assert_eq!(advisory_ids(&report), vec!["GO-1"]);
+    assert_eq!(report["results"][0]["start"]["line"], 1);
+    assert!(report.get("warnings").is_none());
+}
+
+#[test]
+fn go_sum_without_go_mod_uses_the_highest_version_and_says_so() {
+    let project = Project::new();
+    project.write("go.sum", GO_SUM);
+
+    let report = project.scan();
+    assert_eq!(dependencies(&report), deps(&[("Go", "golang.org/x/text", "0.3.7")]));
+    let warnings = report["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].as_str().unwrap().contains("go.mod"));
+}
+
+/// Whether `version` of `pkg` is reported against an advisory with these range `events`.
+fn affected(events: Value, version: &str) -> bool {
+    let project = Project::new();
+    project.write("requirements.txt", &format!("pkg=={}\n", version));
+    project.advisory("PYSEC-1", "PyPI", "pkg", events, json!([]));
+    !advisory_ids(&project.scan()).is_empty()
+}
+
+#[test]
+fn introduced_and_fixed_bound_the_range() {
+    let events = json!([{ "introduced": "1.2.0" }, { "fixed": "1.4.0" }]);
+    assert!(!affected(events.clone(), "1.1.9"));
+    assert!(affected(events.clone(), "1.2.0"));
+    assert!(affected(events.clone(), "1.3.10"));
+    assert!(!affected(events.clone(), "1.4.0"));
+    assert!(affected(events, "1.4.0rc1"));
+}
+
+#[test]
+fn a_range_introduced_and_fixed_at_the_same_version_is_empty() {
+    assert!(!affected(json!([{ "introduced": "1.0.0" }, { "fixed": "1.0.0" }]), "1.0.0"));
+    assert!(!affected(json!([{ "fixed": "1.0.0" }, { "introduced": "1.0.0" }]), "1.0.0"));
+}
+
+#[test]
+fn last_affected_is_inclusive() {
+    let events = json!([{ "introduced": "0" }, { "last_affected": "2.1.0" }]);
+    assert!(affected(events.clone(), "0.1.0"));
+    assert!(affected(events.clone(), "2.1.0"));
+    assert!(!affected(events, "2.1.1"));
+    assert!(affected(json!([{ "introduced": "2.0.0" }, { "last_affected": "2.0.0" }]), "2.0.0"));
+}
+
+#[test]
+fn unsorted_events_are_evaluated_in_version_order() {
+    let events = json!([{ "introduced": "2.0.0" }, { "fixed": "1.5.0" }, { "fixed": "2.2.0" }, { "introduced": "1.0.0" }]);
+    assert!(affected(events.clone(), "1.2.0"));
+    assert!(!affected(events.clone(), "1.7.0"));
+    assert!(affected(events.clone(), "2.1.0"));
+    assert!(!affected(events, "2.2.0"));
+}
+
+#[test]
+fn listed_versions_and_normalized_names_match() {
+    let project = Project::new();
+    project.write("requirements.txt", "Typing_Extensions==4.0.0\n");
+    project.advisory("PYSEC-2", "PyPI", "typing-extensions", json!([]), json!(["4.0.0"]));
+    assert_eq!(advisory_ids(&project.scan()), vec!["PYSEC-2"]);
+}
+
+#[test]
+fn missing_advisory_db_is_an_error() {
+    let project = Project::new();
+    let mut server = Server::initialized();
+    let arguments = json!({ "paths": [project.dir.path()], "advisory_db": Path::new("/nonexistent/osv") });
+    let response = server.request(2, "tools/call", Some(json!({ "name": "semgrep_supply_chain_scan", "arguments": arguments })));
+    assert_eq!(common::error_code(&response), Some(-32603), "{}", response);
+    assert!(response["error"]["message"].as_str().unwrap().contains("/nonexistent/osv"));
+}
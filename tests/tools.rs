@@ -0,0 +1,80 @@
+//! Which tools are offered, and how changes to that set are announced.
+
+mod common;
+
+use common::{error_code, Server};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+fn tool_names(server: &mut Server, id: i64) -> Vec<String> {
+    let response = server.request(id, "tools/list", None);
+    response["result"]["tools"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap().to_string()).collect()
+}
+
+#[test]
+fn app_tools_need_a_token() {
+    let mut server = Server::initialized();
+    let names = tool_names(&mut server, 2);
+    assert!(names.contains(&"semgrep_scan".to_string()));
+    assert!(!names.contains(&"semgrep_findings".to_string()));
+
+    let response = server.request(3, "tools/call", Some(json!({ "name": "semgrep_findings", "arguments": {} })));
+    assert_eq!(error_code(&response), Some(-32601));
+    assert!(response["error"]["message"].as_str().unwrap().contains("SEMGREP_APP_TOKEN"));
+
+    let mut server = Server::start_with(&[], &[("SEMGREP_APP_TOKEN", "test-token")]).handshake("2024-11-05");
+    let names = tool_names(&mut server, 2);
+    assert!(names.contains(&"semgrep_findings".to_string()));
+    assert!(names.contains(&"list_projects".to_string()));
+    assert!(!names.contains(&"triage_findings".to_string()));
+}
+
+#[test]
+fn allowlist_limits_the_tools() {
+    let mut server = Server::start_with(&["--enable-tools", "semgrep_scan,get_version"], &[]).handshake("2024-11-05");
+    assert_eq!(tool_names(&mut server, 2), vec!["semgrep_scan", "get_version"]);
+
+    let response = server.request(3, "tools/call", Some(json!({ "name": "list_rules", "arguments": {} })));
+    assert_eq!(error_code(&response), Some(-32601));
+}
+
+#[test]
+fn denylist_removes_tools() {
+    let mut server = Server::start_with(&[], &[("SIDERO_DISABLE_TOOLS", "get_version,delete_rule")]).handshake("2024-11-05");
+    let names = tool_names(&mut server, 2);
+    assert!(!names.contains(&"get_version".to_string()));
+    assert!(!names.contains(&"delete_rule".to_string()));
+    assert!(names.contains(&"get_rule".to_string()));
+}
+
+#[test]
+fn read_only_hides_tools_that_change_things() {
+    let mut server = Server::start_with(&["--read-only", "--enable-write-tools"], &[("SEMGREP_APP_TOKEN", "test-token")]).handshake("2024-11-05");
+    let response = server.request(2, "tools/list", None);
+    let tools = response["result"]["tools"].as_array().unwrap();
+    assert!(!tools.is_empty());
+    assert!(tools.iter().all(|t| t["annotations"]["readOnlyHint"] == json!(true)));
+    let names: Vec<&Value> = tools.iter().map(|t| &t["name"]).collect();
+    assert!(!names.contains(&&json!("save_rule")));
+    assert!(!names.contains(&&json!("triage_findings")));
+    assert!(!names.contains(&&json!("start_watch")));
+}
+
+#[test]
+fn semgrep_login_announces_new_tools() {
+    let mut server = Server::initialized();
+    assert!(!tool_names(&mut server, 2).contains(&"semgrep_findings".to_string()));
+
+    let settings = server.home().join(".semgrep");
+    std::fs::create_dir_all(&settings).unwrap();
+    std::fs::write(settings.join("settings.yml"), "has_shown_metrics_notification: true\napi_token: test-token\n").unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let message = server.recv_timeout(deadline.saturating_duration_since(Instant::now())).expect("no tools/list_changed notification");
+        if message["method"] == "notifications/tools/list_changed" {
+            break;
+        }
+    }
+    assert!(tool_names(&mut server, 3).contains(&"semgrep_findings".to_string()));
+}
@@ -0,0 +1,114 @@
+//! Watch mode: live findings for workspace roots, run against a stub `semgrep` on PATH.
+
+mod common;
+
+use common::{error_code, stub_semgrep, Server, SCANNING_SEMGREP};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+fn server(log: &Path) -> Server {
+    let path = stub_semgrep(SCANNING_SEMGREP);
+    Server::start_with(&[], &[("PATH", &path), ("STUB_LOG", log.to_str().unwrap())]).handshake("2024-11-05")
+}
+
+fn call(server: &mut Server, id: i64, tool: &str, arguments: Value) -> Value {
+    let response = server.request(id, "tools/call", Some(json!({ "name": tool, "arguments": arguments })));
+    let text = response["result"]["content"][0]["text"].as_str().unwrap_or_else(|| panic!("{} failed: {}", tool, response));
+    serde_json::from_str(text).unwrap()
+}
+
+/// Waits for the next watch log message, collecting any resource update seen on the way.
+fn next_change(server: &mut Server, updated: &mut Vec<String>) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while Instant::now() < deadline {
+        let Some(message) = server.recv_timeout(Duration::from_millis(200)) else { continue };
+        match message["method"].as_str() {
+            Some("notifications/resources/updated") => updated.push(message["params"]["uri"].as_str().unwrap().to_string()),
+            Some("notifications/message") if message["params"]["data"]["watch_id"].is_string() => return message["params"].clone(),
+            _ => {}
+        }
+    }
+    panic!("no watch notification");
+}
+
+fn files(findings: &Value) -> Vec<String> {
+    findings.as_array().unwrap().iter().map(|f| Path::new(f["path"].as_str().unwrap()).file_name().unwrap().to_string_lossy().to_string()).collect()
+}
+
+#[test]
+fn watch_reports_new_and_resolved_findings() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("src");
+    std::fs::create_dir(&root).unwrap();
+    std::fs::write(root.join("a.js"), "eval(a);\n").unwrap();
+    std::fs::write(dir.path().join("rules.yaml"), "rules: []\n").unwrap();
+    let mut server = server(&dir.path().join("scans.log"));
+    server.request(2, "logging/setLevel", Some(json!({ "level": "info" })));
+
+    let watch = call(&mut server, 3, "start_watch", json!({ "paths": [root], "config": dir.path().join("rules.yaml") }));
+    assert_eq!(watch["watch_id"], "w1");
+    assert_eq!(watch["findings"], 1);
+    let uri = watch["uri"].as_str().unwrap().to_string();
+    assert_eq!(uri, "semgrep://watch/w1");
+    server.request(4, "resources/subscribe", Some(json!({ "uri": uri })));
+
+    let mut updated = Vec::new();
+    std::fs::write(root.join("b.js"), "let b = 1;\neval(b);\n").unwrap();
+    let change = next_change(&mut server, &mut updated);
+    assert_eq!(change["level"], "warning");
+    assert_eq!(files(&change["data"]["new"]), vec!["b.js"]);
+    assert_eq!(change["data"]["resolved"], json!([]));
+    assert_eq!(change["data"]["total"], 2);
+
+    std::fs::write(root.join("a.js"), "let a = 1;\n").unwrap();
+    let change = next_change(&mut server, &mut updated);
+    assert_eq!(change["level"], "info");
+    assert_eq!(change["data"]["new"], json!([]));
+    assert_eq!(files(&change["data"]["resolved"]), vec!["a.js"]);
+    assert_eq!(change["data"]["total"], 1);
+
+    let live = server.request(5, "resources/read", Some(json!({ "uri": uri })));
+    let live: Value = serde_json::from_str(live["result"]["contents"][0]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(files(&live["results"]), vec!["b.js"]);
+    assert!(live["rescans"].as_u64().unwrap() >= 2);
+    assert!(updated.iter().all(|u| u == &uri) && !updated.is_empty());
+
+    let stopped = call(&mut server, 6, "stop_watch", json!({ "watch_id": "w1" }));
+    assert_eq!(stopped["stopped"], json!(["w1"]));
+    let response = server.request(7, "resources/read", Some(json!({ "uri": uri })));
+    assert_eq!(error_code(&response), Some(-32002));
+}
+
+#[test]
+fn rescans_keep_ignore_rules() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("src");
+    std::fs::create_dir(&root).unwrap();
+    std::fs::write(root.join(".semgrepignore"), "generated.js\n").unwrap();
+    std::fs::write(root.join("a.js"), "let a = 1;\n").unwrap();
+    std::fs::write(dir.path().join("rules.yaml"), "rules: []\n").unwrap();
+    let log = dir.path().join("scans.log");
+    let mut server = server(&log);
+    server.request(2, "logging/setLevel", Some(json!({ "level": "info" })));
+    let watch = call(&mut server, 3, "start_watch", json!({ "paths": [root], "config": dir.path().join("rules.yaml") }));
+    assert_eq!(watch["findings"], 0);
+
+    std::fs::write(root.join("generated.js"), "eval(g);\n").unwrap();
+    std::fs::write(root.join("b.js"), "eval(b);\n").unwrap();
+    let change = next_change(&mut server, &mut Vec::new());
+    assert_eq!(files(&change["data"]["new"]), vec!["b.js"]);
+    assert_eq!(change["data"]["total"], 1);
+
+    // The rescan ran over the watched root, not the changed files alone.
+    let scans = std::fs::read_to_string(&log).unwrap();
+    assert!(scans.lines().skip(1).all(|scan| scan.starts_with(&format!("{} --include", root.display()))), "{}", scans);
+}
+
+#[test]
+fn stop_watch_rejects_unknown_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut server = server(&dir.path().join("scans.log"));
+    let response = server.request(2, "tools/call", Some(json!({ "name": "stop_watch", "arguments": { "watch_id": "w9" } })));
+    assert_eq!(error_code(&response), Some(-32602));
+}
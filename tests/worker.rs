@@ -0,0 +1,201 @@
+//! Persistent `semgrep lsp` workers, run against a stub `semgrep` on PATH.
+
+mod common;
+
+use common::{stub_semgrep, Server};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// A `semgrep` whose `lsp` mode reports every line containing `eval(`, exits when it is sent
+/// a file containing `CRASH`, logs a warning for a file containing `WARN`, and publishes
+/// nothing for Markdown, as Semgrep does for languages it has no rules for. One-off scans
+/// report `stub.cli` instead, so the tests can tell which path a scan took. Every worker
+/// start is appended to `$STUB_LOG`.
+const FAKE_SEMGREP: &str = r#"#!/usr/bin/env python3
+import json, os, sys
+
+def read():
+    length = None
+    while True:
+        line = sys.stdin.buffer.readline()
+        if not line:
+            sys.exit(0)
+        line = line.strip()
+        if not line:
+            break
+        name, _, value = line.partition(b":")
+        if name.lower() == b"content-length":
+            length = int(value)
+    return json.loads(sys.stdin.buffer.read(length))
+
+def send(message):
+    body = json.dumps(message).encode()
+    sys.stdout.buffer.write(b"Content-Length: %d\r\n\r\n" % len(body) + body)
+    sys.stdout.buffer.flush()
+
+if sys.argv[1:] == ["--version"]:
+    print("1.99.0")
+elif sys.argv[1:] == ["lsp"]:
+    with open(os.environ["STUB_LOG"], "a") as log:
+        log.write("start\n")
+    while True:
+        message = read()
+        method = message.get("method")
+        if method == "initialize":
+            send({"jsonrpc": "2.0", "id": message["id"], "result": {"capabilities": {}}})
+        elif method == "textDocument/didOpen":
+            document = message["params"]["textDocument"]
+            if "CRASH" in document["text"]:
+                sys.exit(1)
+            if "WARN" in document["text"]:
+                send({"jsonrpc": "2.0", "method": "window/logMessage", "params": {"type": 2, "message": "rule stub.eval: pattern warning"}})
+            if document["uri"].endswith(".md"):
+                continue
+            diagnostics = [
+                {"range": {"start": {"line": n, "character": line.index("eval(")}, "end": {"line": n, "character": len(line)}},
+                 "severity": 1, "code": "stub.eval", "source": "Semgrep", "message": "eval found"}
+                for n, line in enumerate(document["text"].splitlines()) if "eval(" in line
+            ]
+            send({"jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": {"uri": document["uri"], "diagnostics": diagnostics}})
+else:
+    print(json.dumps({"results": [{"check_id": "stub.cli", "path": sys.argv[-1], "start": {"line": 1, "col": 1}, "end": {"line": 1, "col": 2}, "extra": {"message": "cli", "severity": "INFO"}}], "errors": []}))
+"#;
+
+/// A config whose rules cover JavaScript.
+const RULES: &str = "rules:\n  - id: stub.eval\n    pattern: eval(...)\n    message: eval found\n    languages: [javascript]\n    severity: ERROR\n";
+
+/// Scan targets and the worker start log for one test.
+struct Stub {
+    dir: TempDir,
+}
+
+impl Stub {
+    fn new() -> Self {
+        Self { dir: tempfile::tempdir().unwrap() }
+    }
+
+    fn server(&self, args: &[&str]) -> Server {
+        let path = stub_semgrep(FAKE_SEMGREP);
+        let log = self.dir.path().join("starts.log");
+        Server::start_with(args, &[("PATH", &path), ("STUB_LOG", log.to_str().unwrap())]).handshake("2024-11-05")
+    }
+
+    fn file(&self, name: &str, contents: &str) -> String {
+        let path = self.dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.display().to_string()
+    }
+
+    /// How many times a worker was started.
+    fn starts(&self) -> usize {
+        std::fs::read_to_string(self.dir.path().join("starts.log")).map(|log| log.lines().count()).unwrap_or(0)
+    }
+}
+
+fn scan_output(server: &mut Server, id: i64, tool: &str, arguments: Value) -> Value {
+    let response = server.request(id, "tools/call", Some(json!({ "name": tool, "arguments": arguments })));
+    let text = response["result"]["content"][0]["text"].as_str().unwrap_or_else(|| panic!("scan failed: {}", response));
+    serde_json::from_str(text).unwrap()
+}
+
+fn scan(server: &mut Server, id: i64, tool: &str, arguments: Value) -> Vec<Value> {
+    scan_output(server, id, tool, arguments)["results"].as_array().unwrap().clone()
+}
+
+fn check_ids(results: &[Value]) -> Vec<&str> {
+    results.iter().map(|r| r["check_id"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn workers_are_reused_per_config() {
+    let stub = Stub::new();
+    let mut server = stub.server(&["--semgrep-worker", "--no-scan-cache"]);
+    let target = stub.file("app.js", "let a = 1;\neval(input);\n");
+    let config = stub.file("rules.yaml", RULES);
+
+    let results = scan(&mut server, 2, "semgrep_scan", json!({ "paths": [target], "config": config }));
+    assert_eq!(check_ids(&results), vec!["stub.eval"]);
+    assert_eq!(results[0]["path"], target);
+    assert_eq!(results[0]["start"], json!({ "line": 2, "col": 1 }));
+    assert_eq!(results[0]["extra"]["lines"], "eval(input);");
+
+    scan(&mut server, 3, "semgrep_scan", json!({ "paths": [target], "config": config }));
+    assert_eq!(stub.starts(), 1);
+
+    let rule = "rules:\n  - id: x\n    pattern: eval(...)\n    message: x\n    languages: [js]\n    severity: ERROR\n";
+    let results = scan(&mut server, 4, "semgrep_scan_with_custom_rule", json!({ "rule": rule, "code_files": [target] }));
+    assert_eq!(check_ids(&results), vec!["stub.eval"]);
+    scan(&mut server, 5, "semgrep_scan_with_custom_rule", json!({ "rule": rule, "code_files": [target] }));
+    assert_eq!(stub.starts(), 2);
+}
+
+#[test]
+fn crashed_workers_are_restarted() {
+    let stub = Stub::new();
+    let mut server = stub.server(&["--semgrep-worker", "--no-scan-cache"]);
+    let config = stub.file("rules.yaml", RULES);
+    let crash = stub.file("crash.js", "CRASH\n");
+    let target = stub.file("app.js", "eval(input);\n");
+
+    // The worker crashes, its replacement crashes too, and the scan falls back to the CLI.
+    let results = scan(&mut server, 2, "semgrep_scan", json!({ "paths": [crash], "config": config }));
+    assert_eq!(check_ids(&results), vec!["stub.cli"]);
+    assert_eq!(stub.starts(), 2);
+
+    let results = scan(&mut server, 3, "semgrep_scan", json!({ "paths": [target], "config": config }));
+    assert_eq!(check_ids(&results), vec!["stub.eval"]);
+    assert_eq!(stub.starts(), 3);
+}
+
+#[test]
+fn scans_without_a_worker_run_the_cli() {
+    let stub = Stub::new();
+    let mut server = stub.server(&[]);
+    let config = stub.file("rules.yaml", RULES);
+    let target = stub.file("app.js", "eval(input);\n");
+
+    let results = scan(&mut server, 2, "semgrep_scan", json!({ "paths": [target], "config": config }));
+    assert_eq!(check_ids(&results), vec!["stub.cli"]);
+    assert_eq!(stub.starts(), 0);
+}
+
+#[test]
+fn workers_open_only_files_the_rules_cover() {
+    let stub = Stub::new();
+    let mut server = stub.server(&["--semgrep-worker", "--no-scan-cache"]);
+    let config = stub.file("rules.yaml", RULES);
+    std::fs::create_dir_all(stub.dir.path().join("src/generated")).unwrap();
+    let readme = stub.file("src/README.md", "# eval(docs)\n");
+    let target = stub.file("src/app.js", "eval(input); // WARN\n");
+    stub.file("src/generated/out.js", "eval(generated);\n");
+    stub.file("src/.semgrepignore", "generated/\n");
+
+    // Semgrep would never answer for the Markdown file, so the scan must not wait on it.
+    let started = Instant::now();
+    let output = scan_output(&mut server, 2, "semgrep_scan", json!({ "paths": [stub.dir.path().join("src")], "config": config }));
+    assert!(started.elapsed() < Duration::from_secs(5), "scan took {:?}", started.elapsed());
+    assert_eq!(output["paths"]["scanned"], json!([target]));
+    assert!(!output.to_string().contains(&readme));
+    assert_eq!(output["errors"][0]["level"], "warn");
+    assert_eq!(output["errors"][0]["message"], "rule stub.eval: pattern warning");
+    assert_eq!(stub.starts(), 1);
+
+    // Worker results have the fields `semgrep scan --json` results have.
+    let result = &output["results"][0];
+    assert_eq!(result["check_id"], "stub.eval");
+    assert_eq!(result["extra"]["metavars"], json!({}));
+    assert!(result["extra"]["fingerprint"].is_string());
+    assert!(result["fingerprint"].is_string());
+}
+
+#[test]
+fn registry_configs_run_the_cli() {
+    let stub = Stub::new();
+    let mut server = stub.server(&["--semgrep-worker", "--no-scan-cache"]);
+    let target = stub.file("app.js", "eval(input);\n");
+
+    let results = scan(&mut server, 2, "semgrep_scan", json!({ "paths": [target], "config": "p/ci" }));
+    assert_eq!(check_ids(&results), vec!["stub.cli"]);
+    assert_eq!(stub.starts(), 0);
+}